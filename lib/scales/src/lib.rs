@@ -18,7 +18,7 @@ pub use serde_json::Value as JsonValue;
 pub use serializer::{to_bytes, to_bytes_with_info, to_vec, to_vec_with_info, Serializer};
 #[cfg(feature = "json")]
 pub use serializer::{to_bytes_from_iter, to_vec_from_iter};
-pub use value::Value;
+pub use value::{SharedRegistry, Value};
 
 use prelude::*;
 use scale_info::{form::PortableForm as Portable, PortableRegistry};
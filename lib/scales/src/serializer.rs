@@ -304,6 +304,11 @@ where
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
+        // a `null` is the absence of a value when the type is an Option
+        if matches!(self.ty, Some(SpecificType::Variant(ref name, _, _)) if name == "Option") {
+            self.out.put_u8(0x00);
+            return Ok(());
+        }
         self.maybe_some()?;
         Ok(())
     }
@@ -391,8 +396,13 @@ where
         Ok(self.into())
     }
 
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
         self.maybe_some()?;
+        // serde_json numbers with arbitrary precision are a struct with the number
+        // as a string, it keeps the type to parse the string as a number
+        if name == "$serde_json::private::Number" {
+            return Ok(TypedSerializer::Empty(self));
+        }
         Ok(self.into())
     }
 
@@ -501,6 +511,11 @@ where
                 self.out.put_i128_le(n);
                 Ok(Some(()))
             }
+            Some(SpecificType::Compact(ty)) => {
+                let n = val.parse().map_err(|_| Error::BadInput("compact".into()))?;
+                self.serialize_compact(ty, n)?;
+                Ok(Some(()))
+            }
             #[cfg(feature = "hex")]
             Some(SpecificType::Bytes(_)) => {
                 if let Some(bytes) = val.strip_prefix("0x") {
@@ -1227,6 +1242,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn json_null_option() -> Result<()> {
+        #[derive(Debug, Encode, Serialize, TypeInfo)]
+        struct Foo {
+            bar: Option<u8>,
+            baz: u8,
+        }
+        let input = Foo { bar: None, baz: 1 };
+        let mut out = Vec::<u8>::new();
+        let expected = input.encode();
+        let (id, reg) = register(&input);
+
+        let json_input = to_value(&input).unwrap();
+        to_bytes_with_info(&mut out, &json_input, Some((&reg, id)))?;
+
+        assert_eq!(out, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn json_str_compact() -> Result<()> {
+        #[derive(Debug, Encode, TypeInfo)]
+        struct Foo {
+            #[codec(compact)]
+            bar: u64,
+        }
+        let foo = Foo { bar: 1_000_000 };
+        let (ty, reg) = register(&foo);
+
+        let input = vec![("bar", crate::JsonValue::String("1000000".into()))];
+        let out = to_vec_from_iter(input, (&reg, ty))?;
+
+        assert_eq!(out, foo.encode());
+        Ok(())
+    }
+
     #[test]
     fn test_unordered_iter() -> Result<()> {
         #[derive(Debug, Encode, TypeInfo, Serialize)]
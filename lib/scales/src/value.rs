@@ -1,7 +1,6 @@
 use crate::{EnumVariant, SpecificType};
-use alloc::{collections::BTreeMap, vec::Vec};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use bytes::{Buf, Bytes};
-use codec::Encode;
 use core::{convert::TryInto, str};
 use scale_info::{prelude::*, PortableRegistry, TypeDefPrimitive as Primitive};
 use serde::ser::{SerializeMap, SerializeSeq, SerializeTuple, SerializeTupleStruct};
@@ -11,12 +10,32 @@ type Type = scale_info::Type<scale_info::form::PortableForm>;
 type TypeId = u32;
 type TypeDef = scale_info::TypeDef<scale_info::form::PortableForm>;
 
+/// A type registry that values can own a reference to, like the metadata of a chain
+pub type SharedRegistry = Arc<dyn AsRef<PortableRegistry> + Send + Sync>;
+
 /// A container for SCALE encoded data that can serialize types directly
 /// with the help of a type registry and without using an intermediate representation.
 pub struct Value<'a> {
     data: Bytes,
     ty_id: TypeId,
-    registry: &'a PortableRegistry,
+    registry: Registry<'a>,
+}
+
+#[derive(Clone)]
+enum Registry<'a> {
+    Borrowed(&'a PortableRegistry),
+    Shared(SharedRegistry),
+}
+
+impl core::ops::Deref for Registry<'_> {
+    type Target = PortableRegistry;
+
+    fn deref(&self) -> &PortableRegistry {
+        match self {
+            Registry::Borrowed(registry) => registry,
+            Registry::Shared(registry) => registry.as_ref().as_ref(),
+        }
+    }
 }
 
 impl<'a> Value<'a> {
@@ -24,17 +43,36 @@ impl<'a> Value<'a> {
         Value {
             data: data.into(),
             ty_id,
-            registry,
+            registry: Registry::Borrowed(registry),
+        }
+    }
+
+    /// A value that keeps the registry of its type alive instead of borrowing it
+    pub fn shared(data: impl Into<Bytes>, ty_id: u32, registry: SharedRegistry) -> Self {
+        Value {
+            data: data.into(),
+            ty_id,
+            registry: Registry::Shared(registry),
         }
     }
 
+    /// The same value keeping the given registry alive, it must be the registry
+    /// the value was created with
+    pub fn into_shared<'b>(self, registry: SharedRegistry) -> Value<'b> {
+        Value::shared(self.data, self.ty_id, registry)
+    }
+
     fn new_value(&self, data: &mut Bytes, ty_id: TypeId) -> Self {
         let size = self.ty_size(data.chunk(), ty_id);
-        Value::new(data.copy_to_bytes(size), ty_id, self.registry)
+        Value {
+            data: data.copy_to_bytes(size),
+            ty_id,
+            registry: self.registry.clone(),
+        }
     }
 
     #[inline]
-    fn resolve(&self, ty: TypeId) -> &'a Type {
+    fn resolve(&self, ty: TypeId) -> &Type {
         self.registry.resolve(ty).expect("in registry")
     }
 
@@ -107,7 +145,7 @@ impl Serialize for Value<'_> {
         let ty = self.resolve(self.ty_id);
 
         use SpecificType::*;
-        match (ty, self.registry).into() {
+        match (ty, &*self.registry).into() {
             Bool => ser.serialize_bool(data.get_u8() != 0),
             U8 => ser.serialize_u8(data.get_u8()),
             U16 => ser.serialize_u16(data.get_u16_le()),
@@ -126,16 +164,23 @@ impl Serialize for Value<'_> {
                     .expect("not found in registry")
                     .type_def;
 
-                use codec::Compact;
+                use codec::{Compact, Decode};
+                let input = &mut data.chunk();
                 match type_def {
+                    TypeDef::Primitive(Primitive::U8) => {
+                        ser.serialize_u8(Compact::<u8>::decode(input).expect("compact").0)
+                    }
+                    TypeDef::Primitive(Primitive::U16) => {
+                        ser.serialize_u16(Compact::<u16>::decode(input).expect("compact").0)
+                    }
                     TypeDef::Primitive(Primitive::U32) => {
-                        ser.serialize_bytes(&Compact(data.get_u32_le()).encode())
+                        ser.serialize_u32(Compact::<u32>::decode(input).expect("compact").0)
                     }
                     TypeDef::Primitive(Primitive::U64) => {
-                        ser.serialize_bytes(&Compact(data.get_u64_le()).encode())
+                        ser.serialize_u64(Compact::<u64>::decode(input).expect("compact").0)
                     }
                     TypeDef::Primitive(Primitive::U128) => {
-                        ser.serialize_bytes(&Compact(data.get_u128_le()).encode())
+                        ser.serialize_u128(Compact::<u128>::decode(input).expect("compact").0)
                     }
                     _ => unimplemented!(),
                 }
@@ -247,7 +292,8 @@ fn compact_size(data: &[u8]) -> usize {
         0 => 1,
         1 => 2,
         2 => 4,
-        _ => todo!(),
+        // big integer mode, the upper bits tell the length of the number
+        _ => (data[0] >> 2) as usize + 5,
     }
 }
 
@@ -369,6 +415,29 @@ mod tests {
         assert_eq!("{\"bar\":\"BAZ\"}", out_value);
     }
 
+    #[cfg(feature = "json")]
+    #[test]
+    fn shared_registry() {
+        #[derive(Encode, TypeInfo)]
+        struct Foo {
+            bar: Vec<u8>,
+        }
+        struct Metadata(PortableRegistry);
+        impl AsRef<PortableRegistry> for Metadata {
+            fn as_ref(&self) -> &PortableRegistry {
+                &self.0
+            }
+        }
+        let in_value = Foo { bar: vec![1, 2] };
+        let (id, reg) = register(&in_value);
+
+        let value = Value::new(in_value.encode(), id, &reg);
+        let shared = value.into_shared(Arc::new(Metadata(reg.clone())));
+        drop(reg);
+        assert_eq!(shared.to_string(), "{\"bar\":[1,2]}");
+        assert_eq!(shared.size(), 3);
+    }
+
     #[cfg(feature = "codec")]
     #[test]
     fn encodable() {
@@ -380,6 +449,32 @@ mod tests {
         assert_eq!(value.encode(), expected);
     }
 
+    #[test]
+    fn serialize_compact() -> Result<(), Error> {
+        #[derive(Encode, TypeInfo)]
+        struct Foo {
+            #[codec(compact)]
+            small: u32,
+            #[codec(compact)]
+            big: u128,
+        }
+        let in_value = Foo {
+            small: 42,
+            big: u64::MAX.into(),
+        };
+        let data = in_value.encode();
+        let (id, reg) = register(&in_value);
+
+        let out_value = Value::new(data, id, &reg);
+
+        assert_eq!(out_value.size(), 1 + 9);
+        assert_eq!(
+            to_value(out_value)?,
+            serde_json::json!({ "small": 42, "big": u64::MAX })
+        );
+        Ok(())
+    }
+
     #[test]
     fn serialize_u8() -> Result<(), Error> {
         let in_value = u8::MAX;
@@ -4,11 +4,10 @@ use async_std::{
     path::PathBuf,
     task::block_on,
 };
-use codec::Decode;
-use opts::Opt;
+use codec::{Decode, Encode};
+use opts::{Cmd, Opt};
 use structopt::StructOpt;
-use sube::{sube, Backend, Metadata};
-use url::Url;
+use sube::{ConfirmationLevel, JsonValue, Metadata, Response, Scheme, SigningPayload, SubeBuilder};
 
 mod opts;
 
@@ -21,22 +20,113 @@ async fn run() -> Result<()> {
         .init()
         .unwrap();
 
-    let url = chain_string_to_url(&opt.chain)?;
+    let meta = match &opt.metadata {
+        Some(m) => Some(
+            get_meta_from_fs(m)
+                .await
+                .ok_or_else(|| anyhow!("Couldn't read Metadata from file"))?,
+        ),
+        None => None,
+    };
 
-    // let backend = sube::ws::Backend::new_ws2(url.as_str()).await?;
-    let backend = sube::http::Backend::new(url.as_str());
+    let out = match opt.cmd {
+        None => {
+            let input = opt.input.ok_or_else(|| anyhow!("Missing query"))?;
+            let url = chain_path(&opt.chain, &input);
+            let builder = SubeBuilder::default().with_url(&url);
+            let res = match meta {
+                Some(meta) => builder.with_meta(meta).await?,
+                None => builder.await?,
+            };
+            opt.output.format(res)?
+        }
+        Some(Cmd::Payload {
+            call,
+            body,
+            account,
+            scheme,
+            nonce,
+            genesis_hash,
+            spec_version,
+            tx_version,
+            mortality,
+            tip,
+        }) => {
+            let url = chain_path(&opt.chain, &call);
+            let body: JsonValue = serde_json::from_str(&body)?;
+            let mut builder = SubeBuilder::default().with_url(&url);
+            if let Some(meta) = meta {
+                builder = builder.with_meta(meta);
+            }
+            let mut builder = builder.with_body(body);
+            if let Some(nonce) = nonce {
+                builder = builder.with_nonce(nonce);
+            }
+            if let Some(hash) = genesis_hash {
+                let hash = from_hex(&hash)?
+                    .try_into()
+                    .map_err(|_| anyhow!("Genesis hash must be 32 bytes long"))?;
+                builder = builder.with_genesis_hash(hash);
+            }
+            match (spec_version, tx_version) {
+                (Some(spec), Some(tx)) => builder = builder.with_runtime_version(spec, tx),
+                (None, None) => {}
+                _ => return Err(anyhow!("Both the spec and transaction versions are needed")),
+            }
+            if let Some(period) = mortality {
+                builder = builder.with_mortality(period);
+            }
+            if let Some(tip) = tip {
+                builder = builder.with_tip(tip);
+            }
 
-    let meta = if let Some(m) = opt.metadata {
-        get_meta_from_fs(&m)
-            .await
-            .ok_or_else(|| anyhow!("Couldn't read Metadata from file"))?
-    } else {
-        backend.metadata().await?
+            let scheme = match scheme.to_lowercase().as_str() {
+                "sr25519" => Scheme::Sr25519,
+                "ed25519" => Scheme::Ed25519,
+                "ecdsa" => Scheme::Ecdsa,
+                _ => return Err(anyhow!("Unknown signature scheme {}", scheme)),
+            };
+            let payload = builder
+                .signing_payload(&from_hex(&account)?, scheme)
+                .await?;
+            serde_json::to_vec_pretty(&serde_json::json!({
+                "payload": to_hex(payload.encode()),
+                "message": to_hex(payload.message()),
+            }))?
+        }
+        Some(Cmd::Attach { payload, signature }) => {
+            let payload = SigningPayload::decode(&mut from_hex(&payload)?.as_slice())?;
+            to_hex(payload.attach_signature(from_hex(&signature)?)).into_bytes()
+        }
+        Some(Cmd::Decode { extrinsic }) => {
+            let meta = match meta {
+                Some(meta) => meta,
+                None => {
+                    let url = chain_path(&opt.chain, "_meta");
+                    match SubeBuilder::default().with_url(&url).await? {
+                        Response::Meta(meta) => Metadata::clone(&meta),
+                        _ => return Err(anyhow!("Couldn't get the metadata of the chain")),
+                    }
+                }
+            };
+            let decoded = sube::extrinsic::decode(&meta, &from_hex(&extrinsic)?)?;
+            serde_json::to_vec_pretty(&decoded)?
+        }
+        Some(Cmd::Broadcast { extrinsic, wait }) => {
+            let level: ConfirmationLevel = match wait {
+                Some(level) => serde_json::from_value(JsonValue::String(level))?,
+                None => ConfirmationLevel::Submitted,
+            };
+            let res = SubeBuilder::default()
+                .with_url(&opt.chain)
+                .with_confirmation(level)
+                .broadcast(from_hex(&extrinsic)?)
+                .await?;
+            opt.output.format(res)?
+        }
     };
 
-    let res = sube::<u8>(backend, &meta, &opt.input, None, |_, _| {}).await?;
-
-    io::stdout().write_all(&opt.output.format(res)?).await?;
+    io::stdout().write_all(&out).await?;
     writeln!(io::stdout()).await?;
     Ok(())
 }
@@ -53,32 +143,20 @@ fn main() {
     })
 }
 
-// Function that tries to be "smart" about what the user might want to actually connect to
-fn chain_string_to_url(chain: &str) -> Result<Url> {
-    let chain = if !chain.starts_with("ws://")
-        && !chain.starts_with("wss://")
-        && !chain.starts_with("http://")
-        && !chain.starts_with("https://")
-    {
-        ["wss", &chain].join("://")
-    } else {
-        chain.into()
-    };
-
-    let mut url = Url::parse(&chain)?;
-
-    if url.host_str().eq(&Some("localhost")) && url.port().is_none() {
-        const WS_PORT: u16 = 9944;
-        const HTTP_PORT: u16 = 9933;
-        let port = match url.scheme() {
-            "ws" => WS_PORT,
-            _ => HTTP_PORT,
-        };
+fn chain_path(chain: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        chain.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
 
-        url.set_port(Some(port)).expect("known port");
-    }
+fn from_hex(input: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(input.trim().trim_start_matches("0x"))?)
+}
 
-    Ok(url)
+fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(bytes))
 }
 
 async fn get_meta_from_fs(path: &PathBuf) -> Option<Metadata> {
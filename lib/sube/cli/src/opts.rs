@@ -24,8 +24,69 @@ pub(crate) struct Opt {
     #[structopt(short, long, parse(from_occurrences))]
     pub verbose: usize,
 
-    #[structopt(value_name = "QUERY/CALL")]
-    pub input: String,
+    #[structopt(subcommand)]
+    pub cmd: Option<Cmd>,
+
+    /// Storage query or `_meta`, `_api/...` and `_events` paths when no command is given
+    #[structopt(value_name = "QUERY")]
+    pub input: Option<String>,
+}
+
+/// Extrinsics are built, signed and broadcast in separate steps so they
+/// can be signed elsewhere, e.g. with an air-gapped wallet
+#[derive(StructOpt, Debug)]
+pub(crate) enum Cmd {
+    /// Print the SCALE encoded signing payload of a call and the message to sign.
+    ///
+    /// Nothing is fetched from the chain when the metadata, nonce and genesis hash are given
+    Payload {
+        /// Call like `balances/transfer_keep_alive`
+        #[structopt(value_name = "CALL")]
+        call: String,
+        /// JSON arguments of the call
+        #[structopt(short, long)]
+        body: String,
+        /// Hex encoded account that signs the extrinsic
+        #[structopt(short, long)]
+        account: String,
+        /// Signature scheme of the account (sr25519,ed25519,ecdsa)
+        #[structopt(short, long, default_value = "sr25519")]
+        scheme: String,
+        #[structopt(short, long)]
+        nonce: Option<u64>,
+        /// Hex encoded hash of the genesis block of the chain
+        #[structopt(short, long)]
+        genesis_hash: Option<String>,
+        #[structopt(long)]
+        spec_version: Option<u32>,
+        #[structopt(long)]
+        tx_version: Option<u32>,
+        /// Number of blocks the extrinsic is valid for, immortal when not set
+        #[structopt(long)]
+        mortality: Option<u64>,
+        #[structopt(long)]
+        tip: Option<u128>,
+    },
+    /// Attach a signature to a signing payload and print the hex encoded extrinsic
+    Attach {
+        #[structopt(value_name = "PAYLOAD")]
+        payload: String,
+        #[structopt(value_name = "SIGNATURE")]
+        signature: String,
+    },
+    /// Decode a hex encoded extrinsic into its address, signature, extensions and call
+    Decode {
+        #[structopt(value_name = "EXTRINSIC")]
+        extrinsic: String,
+    },
+    /// Send a hex encoded signed extrinsic to the chain
+    Broadcast {
+        #[structopt(value_name = "EXTRINSIC")]
+        extrinsic: String,
+        /// Wait until the extrinsic is `included` in a block or `finalized`
+        #[structopt(short, long)]
+        wait: Option<String>,
+    },
 }
 
 #[derive(Debug)]
@@ -0,0 +1,29 @@
+use serde_json::json;
+use sube::{Response, SubeBuilder};
+
+#[async_std::main]
+async fn main() -> sube::Result<()> {
+    let account = [0u8; 32];
+
+    // the signer is never called when estimating fees
+    let signer = sube::SignerFn::try_from((account, |_: &[u8]| async {
+        Err::<sube::Bytes<64>, _>(sube::Error::Signing)
+    }))?;
+
+    let response = SubeBuilder::default()
+        .with_url("wss://rococo-rpc.polkadot.io/balances/transfer")
+        .with_body(json!({
+            "dest": {
+                "Id": account
+            },
+            "value": 100000
+        }))
+        .with_signer(signer)
+        .estimate_fee()
+        .await?;
+
+    if let Response::Fee(fee) = response {
+        println!("Partial fee: {} ({:?})", fee.partial_fee, fee.weight);
+    }
+    Ok(())
+}
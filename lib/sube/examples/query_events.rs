@@ -0,0 +1,18 @@
+use env_logger;
+use sube::{sube, Response, Result};
+
+#[async_std::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    // events emitted by the second extrinsic of the block
+    let result = sube!("wss://rococo-rpc.polkadot.io/_events/1?at=2067321").await?;
+
+    if let Response::Events(events) = result {
+        for event in events {
+            println!("{}::{} {}", event.pallet, event.name, event.data);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use env_logger;
+use sube::{failover, Response, Result, SubeBuilder, SubeClient};
+
+#[async_std::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    // requests go to the endpoint that answers the fastest and
+    // to the other ones when it goes down
+    let client = SubeClient::new().with_failover_options(failover::Options {
+        strategy: failover::Strategy::Latency,
+        ..Default::default()
+    });
+
+    let response = SubeBuilder::default()
+        .with_client(&client)
+        .with_url("wss://kreivo.io,https://kreivo.io/system/number")
+        .await?;
+
+    if let Response::Value(number) = response {
+        println!("{}", number);
+    }
+
+    Ok(())
+}
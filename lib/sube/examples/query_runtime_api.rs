@@ -0,0 +1,15 @@
+use env_logger;
+use sube::{sube, Response, Result};
+
+#[async_std::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let response = sube!("wss://rococo-rpc.polkadot.io/_api/AccountNonceApi/account_nonce/0x3c85f79f28628bee75cdb9eddfeae249f813fad95f84120d068fbc990c4b717d").await?;
+
+    if let Response::Value(nonce) = response {
+        println!("Account nonce: {}", nonce);
+    }
+
+    Ok(())
+}
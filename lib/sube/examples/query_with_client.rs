@@ -0,0 +1,29 @@
+use env_logger;
+use sube::{MetadataCache, Response, Result, SubeBuilder, SubeClient};
+
+#[async_std::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    // keep the connection and metadata of a single chain at a time,
+    // metadata is downloaded again only after runtime upgrades
+    let client = SubeClient::new()
+        .with_capacity(1)
+        .with_metadata_cache(MetadataCache::default());
+
+    for url in [
+        "wss://rococo-rpc.polkadot.io/system/number",
+        "wss://kreivo.io/system/number",
+        "wss://rococo-rpc.polkadot.io/system/number",
+    ] {
+        let response = SubeBuilder::default()
+            .with_client(&client)
+            .with_url(url)
+            .await?;
+
+        if let Response::Value(number) = response {
+            println!("{}: {}", url, number);
+        }
+    }
+    Ok(())
+}
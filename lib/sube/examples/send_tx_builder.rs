@@ -1,7 +1,7 @@
 use libwallet::{self, vault, Account, Signature};
 use serde_json::json;
 use std::{env, error::Error};
-use sube::{Bytes, SubeBuilder, Signer};
+use sube::{Bytes, ConfirmationLevel, SubeBuilder, Signer};
 type Wallet = libwallet::Wallet<vault::Simple<String>>;
 
 #[async_std::main]
@@ -20,7 +20,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let account = wallet.default_account().unwrap();
 
-    let signer = sube::SignerFn::from((
+    let signer = sube::SignerFn::try_from((
         account.public().as_ref(),
         |message: &[u8]| {
             let message = message.to_vec();
@@ -36,7 +36,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 Ok::<Bytes<64>, sube::Error>(result)
             }
         },
-    ));
+    ))?;
 
     let response = SubeBuilder::default()
         .with_url("wss://rococo-rpc.polkadot.io/balances/transfer")
@@ -47,6 +47,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
             "value": 100000
         }))
         .with_signer(signer)
+        .with_mortality(64)
+        .with_confirmation(ConfirmationLevel::Included)
         .await.map_err(|_| format!("Failed to send tx"));
 
     println!("{:?}", response);
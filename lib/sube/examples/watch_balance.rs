@@ -0,0 +1,20 @@
+use async_std::stream::StreamExt;
+use env_logger;
+use sube::{sube, Response, Result};
+
+#[async_std::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let response = sube!("wss://rococo-rpc.polkadot.io/system/account/0x3c85f79f28628bee75cdb9eddfeae249f813fad95f84120d068fbc990c4b717d?watch").await?;
+
+    if let Response::Updates(mut updates) = response {
+        while let Some(update) = updates.next().await {
+            if let Response::Value(account) = update? {
+                println!("Account info: {}", account);
+            }
+        }
+    }
+
+    Ok(())
+}
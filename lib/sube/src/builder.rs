@@ -1,34 +1,50 @@
-#[cfg(any(feature = "http", feature = "http-web"))]
-use crate::http::Backend as HttpBackend;
-#[cfg(any(feature = "http", feature = "http-web", feature = "ws", feature = "js"))]
-use crate::rpc::RpcClient;
-#[cfg(feature = "ws")]
-use crate::ws::Backend as WSBackend;
+use crate::client::{default_client, SubeClient};
 use crate::{
-    meta::BlockInfo, Backend, Error, ExtrinsicBody, Metadata, Response, Result as SubeResult,
-    Signer,
+    meta::Meta as _, Batch, BatchMode, Call, ConfirmationLevel, Error, ExtrinsicBody,
+    ExtrinsicMode, Metadata, Offline, Response, Result as SubeResult, Scheme, Signer,
+    SigningPayload, Wrapped, Wrapper,
 };
-use crate::{prelude::*, Offline, RawKey, RawValue};
+use crate::{prelude::*, Extensions, JsonValue, PassAuthenticate, SignedExtension};
 
 use core::future::{Future, IntoFuture};
 use url::Url;
 
 pub struct SubeBuilder<'a, Body, Signer> {
     url: Option<&'a str>,
+    client: Option<&'a SubeClient>,
     nonce: Option<u64>,
+    mortality: Option<u64>,
+    tip: Option<u128>,
+    fee_asset: Option<JsonValue>,
+    genesis_hash: Option<[u8; 32]>,
+    runtime_version: Option<(u32, u32)>,
     body: Option<Body>,
     signer: Option<Signer>,
     metadata: Option<Metadata>,
+    extensions: Extensions<'a>,
+    mode: ExtrinsicMode,
+    confirmation: ConfirmationLevel,
+    fee_estimation: bool,
 }
 
 impl Default for SubeBuilder<'_, (), ()> {
     fn default() -> Self {
         SubeBuilder {
             url: None,
+            client: None,
             nonce: None,
+            mortality: None,
+            tip: None,
+            fee_asset: None,
+            genesis_hash: None,
+            runtime_version: None,
             body: None,
             signer: None,
             metadata: None,
+            extensions: Extensions::default(),
+            mode: ExtrinsicMode::default(),
+            confirmation: ConfirmationLevel::default(),
+            fee_estimation: false,
         }
     }
 }
@@ -41,6 +57,15 @@ impl<'a> SubeBuilder<'a, (), ()> {
         }
     }
 
+    /// Use the connections and metadata kept by the given client instead of
+    /// the ones of the default client shared by all builders
+    pub fn with_client(self, client: &'a SubeClient) -> Self {
+        Self {
+            client: Some(client),
+            ..self
+        }
+    }
+
     pub fn with_meta(self, metadata: Metadata) -> Self {
         Self {
             metadata: Some(metadata),
@@ -52,33 +77,155 @@ impl<'a> SubeBuilder<'a, (), ()> {
         SubeBuilder {
             body: Some(body),
             url: self.url,
+            client: self.client,
             nonce: self.nonce,
+            mortality: self.mortality,
+            tip: self.tip,
+            fee_asset: self.fee_asset,
+            genesis_hash: self.genesis_hash,
+            runtime_version: self.runtime_version,
             signer: self.signer,
             metadata: self.metadata,
+            extensions: self.extensions,
+            mode: self.mode,
+            confirmation: self.confirmation,
+            fee_estimation: self.fee_estimation,
         }
     }
 
+    /// Send several calls in a single extrinsic dispatched by the `Utility` pallet,
+    /// the url only names the chain as each call has its own `pallet/call` path.
+    /// Calls are sent with `batch_all` unless another [`BatchMode`] is given
+    pub fn with_call(self, path: &str, body: JsonValue) -> SubeBuilder<'a, Batch, ()> {
+        self.with_body(Batch::default().with_call(path, body))
+    }
+
+    /// Query several storage items of the chain at the builder's url at once,
+    /// paths are relative to the chain like `system/account/0x...`.
+    /// Values are fetched together in batches when the backend supports it
+    pub async fn query_many(self, paths: &[&str]) -> SubeResult<Vec<Response<'a>>> {
+        let Self {
+            url,
+            client,
+            metadata,
+            ..
+        } = self;
+
+        let (url, endpoints) = chain_string_to_urls(url.ok_or(Error::BadInput)?)?;
+        let block = url
+            .query_pairs()
+            .find(|(k, _)| k == "at")
+            .map(|(_, v)| v.parse::<u32>().map_err(|_| Error::BadInput))
+            .transpose()?;
+
+        let client = match client {
+            Some(client) => client,
+            None => default_client().await,
+        };
+        let (backend, meta) = client.chain(&endpoints, metadata).await?;
+
+        let responses = crate::query_many(&backend.as_ref(), &meta, paths, block).await?;
+        Ok(responses
+            .into_iter()
+            .map(|r| r.into_shared(&meta))
+            .collect())
+    }
+
+    /// Send an extrinsic signed beforehand, e.g. with [`SubeBuilder::sign`],
+    /// to the chain at the builder's url
+    pub async fn broadcast(self, extrinsic: impl AsRef<[u8]>) -> SubeResult<Response<'a>> {
+        let (_, endpoints) = chain_string_to_urls(self.url.ok_or(Error::BadInput)?)?;
+        let client = match self.client {
+            Some(client) => client,
+            None => default_client().await,
+        };
+        let (backend, _) = client.chain(&endpoints, self.metadata).await?;
+
+        crate::broadcast(backend.as_ref(), extrinsic, self.confirmation).await
+    }
+
     async fn build_query(self) -> SubeResult<Response<'a>> {
-        let Self { url, metadata, .. } = self;
+        let Self {
+            url,
+            client,
+            metadata,
+            ..
+        } = self;
 
-        let url = chain_string_to_url(url.ok_or(Error::BadInput)?)?;
+        let (url, endpoints) = chain_string_to_urls(url.ok_or(Error::BadInput)?)?;
 
         let block = url
             .query_pairs()
             .find(|(k, _)| k == "at")
-            .map(|(_, v)| v.parse::<u32>().expect("at query params must be a number"));
+            .map(|(_, v)| v.parse::<u32>().map_err(|_| Error::BadInput))
+            .transpose()?;
+
+        // range of the entries of a storage map to fetch with a partial key
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.parse::<usize>().map_err(|_| Error::BadInput))
+                .transpose()
+        };
+        let offset = param("offset")?.unwrap_or_default();
+        let limit = param("limit")?;
 
         let path = url.path();
 
         log::trace!("building the backend for {}", url);
 
-        let (backend, meta) = get_multi_backend_by_url(url.clone(), metadata).await?;
+        let client = match client {
+            Some(client) => client,
+            None => default_client().await,
+        };
+        let (shared_backend, shared_meta) = client.chain(&endpoints, metadata).await?;
+        let (backend, meta) = (shared_backend.as_ref(), shared_meta.as_ref());
+
+        // `?watch` turns the query into a subscription that streams its updates
+        if url.query_pairs().any(|(k, _)| k == "watch") {
+            return match backend {
+                #[cfg(feature = "ws")]
+                crate::client::AnyBackend::Ws(b) => crate::watch(b, shared_meta, path).await,
+                #[cfg(all(feature = "std", feature = "ws"))]
+                crate::client::AnyBackend::Rpc(b) => crate::watch(b, shared_meta, path).await,
+                _ => Err(Error::Platform(
+                    "Subscriptions require a websocket backend".into(),
+                )),
+            };
+        }
 
-        Ok(match path {
-            "_meta" => Response::Meta(meta),
-            "_meta/registry" => Response::Registry(&meta.types),
-            _ => crate::query(&backend, meta, path, block).await?,
-        })
+        let response = match path {
+            "_meta" => Response::Meta(shared_meta.clone()),
+            "_meta/registry" => Response::Registry(meta.registry().clone()),
+            // runtime API calls, `?ty=<type id>` decodes the output with the given type
+            p if p.trim_matches('/').starts_with("_api/") => {
+                let ty = url
+                    .query_pairs()
+                    .find(|(k, _)| k == "ty")
+                    .map(|(_, v)| v.parse::<u32>().map_err(|_| Error::BadInput))
+                    .transpose()?;
+                crate::call_api(&backend, meta, p, ty, block).await?
+            }
+            // decoded extrinsics of the latest block or of `_block/<number>`
+            p if p.trim_matches('/').starts_with("_block") => {
+                let number = match p.trim_matches('/').strip_prefix("_block/") {
+                    Some(number) => Some(number.parse::<u32>().map_err(|_| Error::BadInput)?),
+                    None => block,
+                };
+                crate::block(&backend, meta, number).await?
+            }
+            // events of the block, `_events/<index>` for the ones of a single extrinsic
+            p if p.trim_matches('/').starts_with("_events") => {
+                let mut events = crate::events::events(&backend, meta, block).await?;
+                if let Some(index) = p.trim_matches('/').strip_prefix("_events/") {
+                    let index = index.parse::<u32>().map_err(|_| Error::BadInput)?;
+                    events.retain(|e| e.extrinsic() == Some(index));
+                }
+                Response::Events(events)
+            }
+            _ => crate::query(&backend, meta, path, block, offset, limit).await?,
+        };
+        Ok(response.into_shared(&shared_meta))
     }
 }
 
@@ -89,16 +236,36 @@ impl<'a, B> SubeBuilder<'a, B, ()> {
             body: self.body,
             metadata: self.metadata,
             nonce: self.nonce,
+            mortality: self.mortality,
+            tip: self.tip,
+            fee_asset: self.fee_asset,
+            genesis_hash: self.genesis_hash,
+            runtime_version: self.runtime_version,
             url: self.url,
+            client: self.client,
+            extensions: self.extensions,
+            // extrinsics given a signer are always signed
+            mode: ExtrinsicMode::Signed,
+            confirmation: self.confirmation,
+            fee_estimation: self.fee_estimation,
         }
     }
 }
 
-impl<'a, B, S> SubeBuilder<'a, B, S>
-where
-    B: serde::Serialize + core::fmt::Debug,
-    S: Signer,
-{
+impl<S> SubeBuilder<'_, Batch, S> {
+    /// Add another call to the batch
+    pub fn with_call(mut self, path: &str, body: JsonValue) -> Self {
+        self.body = self.body.map(|batch| batch.with_call(path, body));
+        self
+    }
+
+    pub fn with_batch_mode(mut self, mode: BatchMode) -> Self {
+        self.body = self.body.map(|batch| batch.with_mode(mode));
+        self
+    }
+}
+
+impl<'a, B, S> SubeBuilder<'a, B, S> {
     pub fn with_nonce(self, nonce: u64) -> Self {
         Self {
             nonce: Some(nonce),
@@ -106,102 +273,335 @@ where
         }
     }
 
-    async fn build_extrinsic(self) -> SubeResult<Response<'a>> {
-        let Self {
-            url,
-            nonce,
-            body,
-            signer,
-            metadata,
-            ..
-        } = self;
+    /// Make the extrinsic valid only for the given number of blocks
+    /// after the current block instead of being immortal
+    pub fn with_mortality(self, period: u64) -> Self {
+        Self {
+            mortality: Some(period),
+            ..self
+        }
+    }
 
-        let url = chain_string_to_url(url.ok_or(Error::BadInput)?)?;
-        let path = url.path();
-        let body = body.ok_or(Error::BadInput)?;
+    /// Sign the extrinsic for the chain with the given genesis hash
+    /// instead of fetching it from the chain
+    pub fn with_genesis_hash(self, hash: [u8; 32]) -> Self {
+        Self {
+            genesis_hash: Some(hash),
+            ..self
+        }
+    }
 
-        let (backend, meta) = get_multi_backend_by_url(url.clone(), metadata).await?;
+    /// Sign the extrinsic for the given spec and transaction versions
+    /// instead of the ones of the runtime the metadata belongs to
+    pub fn with_runtime_version(self, spec_version: u32, transaction_version: u32) -> Self {
+        Self {
+            runtime_version: Some((spec_version, transaction_version)),
+            ..self
+        }
+    }
 
-        Ok(match path {
-            "_meta" => Response::Meta(meta),
-            "_meta/registry" => Response::Registry(&meta.types),
-            _ => {
-                let signer = signer.ok_or(Error::BadInput)?;
+    /// Tip the block author to prioritize the extrinsic
+    pub fn with_tip(self, tip: u128) -> Self {
+        Self {
+            tip: Some(tip),
+            ..self
+        }
+    }
 
-                crate::submit(backend, meta, path, ExtrinsicBody { nonce, body }, signer).await?
-            }
+    /// Pay the fees with the given asset when the chain supports it,
+    /// the asset id is encoded from its JSON representation
+    pub fn with_fee_asset(self, asset_id: JsonValue) -> Self {
+        Self {
+            fee_asset: Some(asset_id),
+            ..self
+        }
+    }
+
+    /// Resolve the submission only once the extrinsic reaches the given level
+    /// of confirmation, it requires a backend that supports subscriptions
+    pub fn with_confirmation(self, level: ConfirmationLevel) -> Self {
+        Self {
+            confirmation: level,
+            ..self
+        }
+    }
+
+    /// Register the handler of a signed extension the chain declares in its metadata,
+    /// it takes precedence over the built-in handler with the same identifier
+    pub fn with_extension(
+        mut self,
+        identifier: &str,
+        extension: impl SignedExtension + 'a,
+    ) -> Self {
+        self.extensions.register(identifier, extension);
+        self
+    }
+
+    /// Authenticate the extrinsic with a Kreivo pass through the `PassAuthenticate`
+    /// extension, `params` are the pass' `AuthenticateParams` in JSON with the
+    /// device and the credential of the authentication
+    pub fn as_pass(self, params: JsonValue) -> Self {
+        self.with_extension("PassAuthenticate", PassAuthenticate(Some(params)))
+    }
+
+    /// Dispatch the call through `Proxy::proxy` on behalf of the `real` account,
+    /// the signer is the proxy. Without a `force_proxy_type` any proxy type
+    /// the signer has for `real` is used
+    pub fn as_proxy(
+        self,
+        real: &[u8],
+        force_proxy_type: Option<&str>,
+    ) -> SubeBuilder<'a, Wrapped<B>, S> {
+        self.wrap(Wrapper::Proxy {
+            real: real.to_vec(),
+            force_proxy_type: force_proxy_type.map(Into::into),
+        })
+    }
+
+    /// Approve the call through `Multisig::as_multi` as the signer, one of the
+    /// signatories of the multisig. The call hash, the timepoint of the approval in
+    /// progress and the weight of the call are computed from the chain
+    pub fn as_multisig(
+        self,
+        threshold: u16,
+        other_signatories: &[&[u8]],
+    ) -> SubeBuilder<'a, Wrapped<B>, S> {
+        self.wrap(Wrapper::Multisig {
+            threshold,
+            other_signatories: other_signatories.iter().map(|s| s.to_vec()).collect(),
+        })
+    }
+
+    /// Wrap the call in another call that dispatches it, wrappers can be nested
+    pub fn wrap(self, wrapper: Wrapper) -> SubeBuilder<'a, Wrapped<B>, S> {
+        SubeBuilder {
+            body: self.body.map(|call| Wrapped::new(wrapper, call)),
+            url: self.url,
+            client: self.client,
+            nonce: self.nonce,
+            mortality: self.mortality,
+            tip: self.tip,
+            fee_asset: self.fee_asset,
+            genesis_hash: self.genesis_hash,
+            runtime_version: self.runtime_version,
+            signer: self.signer,
+            metadata: self.metadata,
+            extensions: self.extensions,
+            mode: self.mode,
+            confirmation: self.confirmation,
+            fee_estimation: self.fee_estimation,
+        }
+    }
+
+    fn tx_data(&mut self) -> SubeResult<ExtrinsicBody<B>> {
+        Ok(ExtrinsicBody {
+            nonce: self.nonce,
+            mortality: self.mortality,
+            tip: self.tip,
+            fee_asset: self.fee_asset.take(),
+            genesis_hash: self.genesis_hash,
+            spec_version: self.runtime_version.map(|(spec, _)| spec),
+            transaction_version: self.runtime_version.map(|(_, tx)| tx),
+            body: self.body.take().ok_or(Error::BadInput)?,
         })
     }
 }
 
-use heapless::FnvIndexMap as Map;
-use no_std_async::Mutex;
+impl<'a, B> SubeBuilder<'a, B, ()>
+where
+    B: Call + core::fmt::Debug,
+{
+    /// Build what `account` has to sign with `scheme` for the extrinsic without signing it,
+    /// the signature is attached later with [`SigningPayload::attach_signature`].
+    /// With the metadata, nonce and genesis hash given an immortal extrinsic
+    /// is built offline, the chain isn't contacted
+    pub async fn signing_payload(
+        mut self,
+        account: &[u8],
+        scheme: Scheme,
+    ) -> SubeResult<SigningPayload> {
+        let tx_data = self.tx_data()?;
+        let (url, endpoints) = chain_string_to_urls(self.url.ok_or(Error::BadInput)?)?;
+        let path = url.path();
+        let extensions = &self.extensions;
 
-static INSTANCE_BACKEND: async_once_cell::OnceCell<
-    Mutex<Map<String, Mutex<&'static AnyBackend>, 16>>,
-> = async_once_cell::OnceCell::new();
+        match self.metadata {
+            Some(meta) if is_offline(&tx_data) => {
+                let chain = Offline(meta);
+                crate::signing_payload(&chain, &chain.0, path, tx_data, account, scheme, extensions)
+                    .await
+            }
+            metadata => {
+                let client = match self.client {
+                    Some(client) => client,
+                    None => default_client().await,
+                };
+                let (backend, meta) = client.chain(&endpoints, metadata).await?;
+                let (backend, meta) = (backend.as_ref(), meta.as_ref());
+                crate::signing_payload(&backend, meta, path, tx_data, account, scheme, extensions)
+                    .await
+            }
+        }
+    }
 
-static INSTANCE_METADATA: async_once_cell::OnceCell<
-    Mutex<Map<String, Mutex<&'static Metadata>, 16>>,
-> = async_once_cell::OnceCell::new();
+    /// Send the call as a bare extrinsic without a signer, for calls the runtime
+    /// validates itself like inherents or the registration of a pass
+    pub fn unsigned(self) -> Self {
+        Self {
+            mode: ExtrinsicMode::Unsigned,
+            ..self
+        }
+    }
 
-async fn get_metadata(backend: &AnyBackend, metadata: Option<Metadata>) -> SubeResult<Metadata> {
-    match metadata {
-        Some(m) => Ok(m),
-        None => backend.metadata().await.map_err(|_| Error::BadMetadata),
+    /// Send the call as a general transaction authorized by the transaction
+    /// extensions of the runtime instead of a signature. The metadata of the
+    /// runtime has to declare the version 5 extrinsic format, V14 and V15
+    /// metadata of current runtimes declare version 4 and building fails
+    pub fn general(self) -> Self {
+        Self {
+            mode: ExtrinsicMode::General,
+            ..self
+        }
     }
-}
 
-async fn get_multi_backend_by_url<'a>(
-    url: Url,
-    metadata: Option<Metadata>,
-) -> SubeResult<(&'a AnyBackend, &'a Metadata)> {
-    let mut instance_backend = INSTANCE_BACKEND
-        .get_or_init(async { Mutex::new(Map::new()) })
-        .await
-        .lock()
-        .await;
-
-    let mut instance_metadata = INSTANCE_METADATA
-        .get_or_init(async { Mutex::new(Map::new()) })
-        .await
-        .lock()
-        .await;
-
-    let base_path = format!(
-        "{}://{}:{}",
-        url.scheme(),
-        url.host_str().expect("url to have a host"),
-        url.port().unwrap_or(80)
-    );
-
-    let cached_b = instance_backend.get(&base_path);
-    let cached_m = instance_metadata.get(&base_path);
-
-    match (cached_b, cached_m) {
-        (Some(b), Some(m)) => {
-            let b = *b.lock().await;
-            let m = *m.lock().await;
-            Ok((b, m))
+    /// Build the unsigned or general extrinsic without submitting it, it's built
+    /// offline when the chain doesn't need to be contacted
+    pub async fn build(mut self) -> SubeResult<Vec<u8>> {
+        let tx_data = self.tx_data()?;
+        let (url, endpoints) = chain_string_to_urls(self.url.ok_or(Error::BadInput)?)?;
+        let (path, mode, extensions) = (url.path(), self.mode, &self.extensions);
+
+        match self.metadata {
+            Some(meta) if mode == ExtrinsicMode::Unsigned || is_offline(&tx_data) => {
+                let chain = Offline(meta);
+                crate::unsigned_extrinsic(&chain, &chain.0, path, tx_data, mode, extensions).await
+            }
+            metadata => {
+                let client = match self.client {
+                    Some(client) => client,
+                    None => default_client().await,
+                };
+                let (backend, meta) = client.chain(&endpoints, metadata).await?;
+                let (backend, meta) = (backend.as_ref(), meta.as_ref());
+                crate::unsigned_extrinsic(&backend, meta, path, tx_data, mode, extensions).await
+            }
         }
-        _ => {
-            let backend = Box::new(get_backend_by_url(url.clone()).await?);
-            let backend = Box::leak::<'static>(backend);
+    }
 
-            instance_backend
-                .insert(base_path.clone(), Mutex::new(backend))
-                .map_err(|_| Error::CantInitBackend)?;
+    /// Build the unsigned or general extrinsic and send it to the chain
+    pub async fn submit(mut self) -> SubeResult<Response<'a>> {
+        let tx_data = self.tx_data()?;
+        let (url, endpoints) = chain_string_to_urls(self.url.ok_or(Error::BadInput)?)?;
 
-            let metadata = Box::new(get_metadata(backend, metadata).await?);
-            let metadata = Box::leak::<'static>(metadata);
+        let client = match self.client {
+            Some(client) => client,
+            None => default_client().await,
+        };
+        let (backend, meta) = client.chain(&endpoints, self.metadata).await?;
+        let (backend, meta) = (backend.as_ref(), meta.as_ref());
+        let extrinsic = crate::unsigned_extrinsic(
+            &backend,
+            meta,
+            url.path(),
+            tx_data,
+            self.mode,
+            &self.extensions,
+        )
+        .await?;
+
+        crate::broadcast(backend, extrinsic, self.confirmation).await
+    }
+}
 
-            instance_metadata
-                .insert(base_path.clone(), Mutex::new(metadata))
-                .map_err(|_| Error::BadMetadata)?;
+impl<'a, B, S> SubeBuilder<'a, B, S>
+where
+    B: Call + core::fmt::Debug,
+    S: Signer,
+{
+    /// Sign the extrinsic without submitting it, it can be broadcast later
+    /// with [`SubeBuilder::broadcast`]. Like [`SubeBuilder::signing_payload`]
+    /// it's built offline when the chain doesn't need to be contacted
+    pub async fn sign(mut self) -> SubeResult<Vec<u8>> {
+        let tx_data = self.tx_data()?;
+        let signer = self.signer.ok_or(Error::BadInput)?;
+        let (url, endpoints) = chain_string_to_urls(self.url.ok_or(Error::BadInput)?)?;
+        let path = url.path();
 
-            Ok((backend, metadata))
+        match self.metadata {
+            Some(meta) if is_offline(&tx_data) => {
+                let chain = Offline(meta);
+                crate::build_extrinsic(&chain, &chain.0, path, tx_data, signer, &self.extensions)
+                    .await
+            }
+            metadata => {
+                let client = match self.client {
+                    Some(client) => client,
+                    None => default_client().await,
+                };
+                let (backend, meta) = client.chain(&endpoints, metadata).await?;
+                let (backend, meta) = (backend.as_ref(), meta.as_ref());
+                crate::build_extrinsic(&backend, meta, path, tx_data, signer, &self.extensions)
+                    .await
+            }
         }
     }
+
+    /// Instead of submitting the extrinsic respond with the fees it would pay,
+    /// the signer is not asked to sign as a dummy signature is used
+    pub fn estimate_fee(self) -> Self {
+        Self {
+            fee_estimation: true,
+            ..self
+        }
+    }
+
+    async fn build_extrinsic(mut self) -> SubeResult<Response<'a>> {
+        let tx_data = self.tx_data()?;
+        let Self {
+            url,
+            client,
+            signer,
+            metadata,
+            extensions,
+            confirmation,
+            fee_estimation,
+            ..
+        } = self;
+
+        let (url, endpoints) = chain_string_to_urls(url.ok_or(Error::BadInput)?)?;
+        let path = url.path();
+
+        let client = match client {
+            Some(client) => client,
+            None => default_client().await,
+        };
+        let (shared_backend, shared_meta) = client.chain(&endpoints, metadata).await?;
+        let (backend, meta) = (shared_backend.as_ref(), shared_meta.as_ref());
+
+        let response = match path {
+            "_meta" => Response::Meta(shared_meta.clone()),
+            "_meta/registry" => Response::Registry(meta.registry().clone()),
+            _ => {
+                let signer = signer.ok_or(Error::BadInput)?;
+
+                if fee_estimation {
+                    crate::estimate_fee(backend, meta, path, tx_data, signer, &extensions).await?
+                } else {
+                    crate::submit(
+                        backend,
+                        meta,
+                        path,
+                        tx_data,
+                        signer,
+                        &extensions,
+                        confirmation,
+                    )
+                    .await?
+                }
+            }
+        };
+        Ok(response.into_shared(&shared_meta))
+    }
 }
 
 pub type BoxFuture<'a, T> = core::pin::Pin<Box<dyn Future<Output = T> + 'a>>;
@@ -217,7 +617,7 @@ impl<'a> IntoFuture for SubeBuilder<'a, (), ()> {
 
 impl<'a, B, S> IntoFuture for SubeBuilder<'a, B, S>
 where
-    B: serde::Serialize + core::fmt::Debug + 'a,
+    B: Call + core::fmt::Debug + 'a,
     S: Signer + 'a,
 {
     type Output = SubeResult<Response<'a>>;
@@ -228,7 +628,25 @@ where
     }
 }
 
-fn chain_string_to_url(chain: &str) -> SubeResult<Url> {
+/// Nothing has to be fetched from the chain to build an immortal extrinsic
+/// when its nonce and the genesis hash of the chain are known
+fn is_offline<B>(tx_data: &ExtrinsicBody<B>) -> bool {
+    tx_data.nonce.is_some() && tx_data.genesis_hash.is_some() && tx_data.mortality.is_none()
+}
+
+/// Several endpoints of the same chain can be given separated by commas like
+/// `wss://a.io,wss://b.io/system/number`, the path and query of the last one
+/// are the ones used
+fn chain_string_to_urls(chain: &str) -> SubeResult<(Url, Vec<Url>)> {
+    let endpoints = chain
+        .split(',')
+        .map(|c| chain_string_to_url(c.trim()))
+        .collect::<SubeResult<Vec<_>>>()?;
+    let url = endpoints.last().cloned().ok_or(Error::BadInput)?;
+    Ok((url, endpoints))
+}
+
+pub(crate) fn chain_string_to_url(chain: &str) -> SubeResult<Url> {
     let chain = if !chain.starts_with("ws://")
         && !chain.starts_with("wss://")
         && !chain.starts_with("http://")
@@ -255,99 +673,6 @@ fn chain_string_to_url(chain: &str) -> SubeResult<Url> {
     Ok(url)
 }
 
-async fn get_backend_by_url(url: Url) -> SubeResult<AnyBackend> {
-    match url.scheme() {
-        #[cfg(feature = "ws")]
-        "ws" | "wss" => Ok(AnyBackend::Ws(RpcClient(
-            WSBackend::new_ws2(url.to_string().as_str()).await?,
-        ))),
-        #[cfg(any(feature = "http", feature = "http-web"))]
-        "http" | "https" => Ok(AnyBackend::Http(RpcClient(HttpBackend::new(url)))),
-        _ => Err(Error::BadInput),
-    }
-}
-
-enum AnyBackend {
-    #[cfg(any(feature = "http", feature = "http-web"))]
-    Http(RpcClient<HttpBackend>),
-    #[cfg(feature = "ws")]
-    Ws(RpcClient<WSBackend>),
-    _Offline(Offline),
-}
-
-impl Backend for &AnyBackend {
-    async fn get_storage_items(
-        &self,
-        keys: Vec<RawKey>,
-        block: Option<u32>,
-    ) -> crate::Result<impl Iterator<Item = (RawKey, Option<RawValue>)>> {
-        let result: Box<dyn Iterator<Item = (RawKey, Option<RawValue>)>> = match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => Box::new(b.get_storage_items(keys, block).await?),
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => Box::new(b.get_storage_items(keys, block).await?),
-            AnyBackend::_Offline(b) => Box::new(b.get_storage_items(keys, block).await?),
-        };
-
-        Ok(result)
-    }
-
-    async fn get_storage_item(&self, key: RawKey, block: Option<u32>) -> crate::Result<Option<Vec<u8>>> {
-        match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => b.get_storage_item(key, block).await,
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => b.get_storage_item(key, block).await,
-            AnyBackend::_Offline(b) => b.get_storage_item(key, block).await,
-        }
-    }
-
-    async fn get_keys_paged(
-        &self,
-        from: RawKey,
-        size: u16,
-        to: Option<RawKey>,
-    ) -> crate::Result<Vec<RawKey>> {
-        match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => b.get_keys_paged(from, size, to).await,
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => b.get_keys_paged(from, size, to).await,
-            AnyBackend::_Offline(b) => b.get_keys_paged(from, size, to).await,
-        }
-    }
-
-    async fn metadata(&self) -> SubeResult<Metadata> {
-        match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => b.metadata().await,
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => b.metadata().await,
-            AnyBackend::_Offline(b) => b.metadata().await,
-        }
-    }
-
-    async fn submit(&self, ext: impl AsRef<[u8]>) -> SubeResult<()> {
-        match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => b.submit(ext).await,
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => b.submit(ext).await,
-            AnyBackend::_Offline(b) => b.submit(ext).await,
-        }
-    }
-
-    async fn block_info(&self, at: Option<u32>) -> SubeResult<BlockInfo> {
-        match self {
-            #[cfg(any(feature = "http", feature = "http-web"))]
-            AnyBackend::Http(b) => b.block_info(at).await,
-            #[cfg(feature = "ws")]
-            AnyBackend::Ws(b) => b.block_info(at).await,
-            AnyBackend::_Offline(b) => b.block_info(at).await,
-        }
-    }
-}
-
 #[macro_export]
 macro_rules! sube {
 
@@ -364,14 +689,14 @@ macro_rules! sube {
 
             let public = $wallet.default_account().expect("to have a default account").public();
 
-            let signer = $crate::SignerFn::from((public, |message: &[u8]| { 
+            let signer = $crate::SignerFn::try_from((public, |message: &[u8]| { 
                 let message = message.to_vec();
                 let wallet = &$wallet;
                 async move {
                     let signature = wallet.sign(&message).await.map_err(|_| sube::Error::Signing)?;
                     Ok::<Bytes<64>, sube::Error>(signature.as_ref().try_into().unwrap())
                 }
-            }));
+            }))?;
 
             builder
                 .with_url($url)
@@ -0,0 +1,94 @@
+/*!
+Downloading the metadata of a chain is often the slowest part of short lived
+processes that only make a few queries. The metadata only changes with runtime
+upgrades, so a cache keyed by the genesis hash of the chain and the spec version
+of its runtime can be reused across processes until the runtime is upgraded.
+*/
+use std::{env, fs, io, path::PathBuf};
+
+use codec::{Decode, Encode};
+
+use crate::Metadata;
+
+/// Directory where SCALE encoded metadata is stored
+#[derive(Clone, Debug)]
+pub struct MetadataCache {
+    dir: PathBuf,
+}
+
+/// Uses the user's cache directory, `$XDG_CACHE_HOME/sube` or `$HOME/.cache/sube`,
+/// and the temporary directory of the system when neither are set
+impl Default for MetadataCache {
+    fn default() -> Self {
+        let dir = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(env::temp_dir);
+        MetadataCache::new(dir.join("sube"))
+    }
+}
+
+impl MetadataCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        MetadataCache { dir: dir.into() }
+    }
+
+    /// Cached metadata of the chain with the given genesis hash at a runtime version
+    pub fn get(&self, genesis: &[u8], spec_version: u32) -> Option<Metadata> {
+        let path = self.path(genesis, spec_version);
+        let bytes = fs::read(&path).ok()?;
+        Metadata::decode(&mut bytes.as_slice())
+            .map_err(|e| log::warn!("ignoring corrupted metadata {:?}: {}", path, e))
+            .ok()
+    }
+
+    pub fn set(&self, genesis: &[u8], spec_version: u32, meta: &Metadata) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(genesis, spec_version);
+        // other processes might be reading the same file, it's replaced at once
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, meta.encode())?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn path(&self, genesis: &[u8], spec_version: u32) -> PathBuf {
+        self.dir
+            .join(format!("{}-{}.scale", hex::encode(genesis), spec_version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS: [u8; 32] = [1; 32];
+
+    fn cache(name: &str) -> MetadataCache {
+        let dir = env::temp_dir().join(format!("sube-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        MetadataCache::new(dir)
+    }
+
+    #[test]
+    fn cached_metadata_roundtrip() {
+        let cache = cache("roundtrip");
+        let meta = crate::mock::fixtures::kreivo();
+
+        assert!(cache.get(&GENESIS, 1).is_none());
+        cache.set(&GENESIS, 1, &meta).unwrap();
+
+        let cached = cache.get(&GENESIS, 1).expect("cached metadata");
+        assert_eq!(cached.encode(), meta.encode());
+        assert!(cache.get(&GENESIS, 2).is_none());
+        assert!(cache.get(&[2; 32], 1).is_none());
+    }
+
+    #[test]
+    fn corrupted_metadata_is_ignored() {
+        let cache = cache("corrupted");
+        fs::create_dir_all(&cache.dir).unwrap();
+        fs::write(cache.path(&GENESIS, 1), b"not metadata").unwrap();
+
+        assert!(cache.get(&GENESIS, 1).is_none());
+    }
+}
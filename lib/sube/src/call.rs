@@ -0,0 +1,357 @@
+/*!
+The body of an extrinsic is encoded into a `RuntimeCall` of the chain, for
+most extrinsics it's the JSON arguments of the `pallet/call` of the path.
+
+Calls that dispatch other calls like the ones of the `Utility` pallet take
+them already encoded, a [`Batch`] collects several `pallet/call` paths with
+their arguments and sends them in a single extrinsic.
+
+A [`Wrapped`] call is dispatched by another call on behalf of a different
+origin, like the account a proxy acts for or a multisig. The extrinsic is
+still signed by its sender.
+*/
+use alloc::collections::BTreeMap;
+use codec::{Compact, Encode};
+use scale_info::TypeDef;
+use serde::Serialize;
+
+use crate::hasher::hash;
+use crate::meta_ext::{Hasher, Meta as _};
+use crate::prelude::*;
+use crate::{Backend, Error, FeeInfo, JsonValue, Metadata, Response, Result};
+
+/// Body of an extrinsic that knows how to encode itself as a `RuntimeCall`,
+/// `path` is the `pallet/call` the extrinsic was built for and `sender` the
+/// account that signs it if any. The chain is there for calls that need to
+/// look something up
+pub trait Call {
+    async fn encode_call(
+        &self,
+        chain: &impl Backend,
+        meta: &Metadata,
+        path: &str,
+        sender: Option<&[u8]>,
+    ) -> Result<Vec<u8>>;
+}
+
+impl<T: Serialize + ?Sized> Call for T {
+    async fn encode_call(
+        &self,
+        _chain: &impl Backend,
+        meta: &Metadata,
+        path: &str,
+        _sender: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        crate::encode_call(meta, path, self)
+    }
+}
+
+/// Argument of a call given to [`encode_call_with`]
+pub(crate) enum Arg {
+    Value(JsonValue),
+    /// Account encoded as the type of the argument, e.g. a `MultiAddress`
+    Account(Vec<u8>),
+    Encoded(Vec<u8>),
+}
+
+/// Encode `pallet/call` with its arguments given by name, the argument that is a
+/// `RuntimeCall` takes `inner` when not given. Missing optional arguments are `None`
+pub(crate) fn encode_call_with(
+    meta: &Metadata,
+    path: &str,
+    mut args: BTreeMap<&str, Arg>,
+    inner: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let (pallet, call, _) = crate::parse_uri(path).ok_or(Error::BadInput)?;
+    let call = call.to_lowercase();
+    let pallet = meta
+        .pallet_by_name(&pallet)
+        .ok_or(Error::PalletNotFound(pallet))?;
+    let calls_ty = pallet.calls().ok_or(Error::CallNotFound)?.ty.id;
+    let runtime_call = meta.extrinsic_types().map(|types| types.call);
+    let registry = meta.registry();
+
+    let call = match &registry
+        .resolve(calls_ty)
+        .ok_or(Error::BadMetadata)?
+        .type_def
+    {
+        TypeDef::Variant(calls) => calls.variants.iter().find(|v| v.name == call),
+        _ => None,
+    }
+    .ok_or(Error::CallNotFound)?;
+
+    let mut encoded = vec![pallet.index(), call.index];
+    for field in call.fields.iter() {
+        let ty = field.ty.id;
+        let arg = field.name.as_deref().and_then(|name| args.remove(name));
+        match (arg, inner) {
+            (Some(Arg::Value(value)), _) => encoded.extend(
+                scales::to_vec_with_info(&value, (registry, ty).into())
+                    .map_err(|e| Error::Encode(e.to_string()))?,
+            ),
+            (Some(Arg::Account(account)), _) => {
+                encoded.extend(crate::extrinsic::encode_account(registry, ty, &account)?)
+            }
+            (Some(Arg::Encoded(value)), _) => encoded.extend(value),
+            (None, Some(inner)) if runtime_call == Some(ty) => encoded.extend(inner),
+            (None, _)
+                if registry
+                    .resolve(ty)
+                    .is_some_and(|t| t.path.segments == ["Option"]) =>
+            {
+                encoded.push(0x00)
+            }
+            (None, _) => return Err(Error::BadInput),
+        }
+    }
+
+    Ok(encoded)
+}
+
+/// Call of the `Utility` pallet a batch is dispatched with
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Stop at the first call that fails, the previous ones are not reverted
+    Batch,
+    /// Revert all the calls if any of them fails
+    #[default]
+    BatchAll,
+    /// Dispatch every call even if some of them fail
+    ForceBatch,
+}
+
+impl BatchMode {
+    pub fn path(&self) -> &'static str {
+        match self {
+            BatchMode::Batch => "utility/batch",
+            BatchMode::BatchAll => "utility/batch_all",
+            BatchMode::ForceBatch => "utility/force_batch",
+        }
+    }
+}
+
+/// Several calls sent in a single extrinsic through the `Utility` pallet,
+/// the path of the extrinsic is ignored as each call has its own
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    mode: BatchMode,
+    calls: Vec<(String, JsonValue)>,
+}
+
+impl Batch {
+    pub fn new(mode: BatchMode) -> Self {
+        Batch {
+            mode,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Add a call like `balances/transfer_keep_alive` with its JSON arguments
+    pub fn with_call(mut self, path: &str, body: JsonValue) -> Self {
+        self.calls.push((path.into(), body));
+        self
+    }
+
+    pub fn with_mode(self, mode: BatchMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub fn mode(&self) -> BatchMode {
+        self.mode
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+impl Call for Batch {
+    async fn encode_call(
+        &self,
+        _chain: &impl Backend,
+        meta: &Metadata,
+        _path: &str,
+        _sender: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        if self.calls.is_empty() {
+            return Err(Error::BadInput);
+        }
+        let calls = self
+            .calls
+            .iter()
+            .map(|(path, body)| crate::encode_call(meta, path, body))
+            .collect::<Result<Vec<_>>>()?;
+        let calls = [Compact(calls.len() as u32).encode(), calls.concat()].concat();
+
+        let args = BTreeMap::from([("calls", Arg::Encoded(calls))]);
+        encode_call_with(meta, self.mode.path(), args, None)
+    }
+}
+
+/// How a [`Wrapped`] call is dispatched
+#[derive(Clone, Debug)]
+pub enum Wrapper {
+    /// `Proxy::proxy` dispatching the call as the `real` account the sender is a
+    /// proxy of, `force_proxy_type` is a variant of the runtime's `ProxyType`
+    Proxy {
+        real: Vec<u8>,
+        force_proxy_type: Option<String>,
+    },
+    /// `Multisig::as_multi` approving the call as one of the signatories of a multisig,
+    /// the timepoint of an approval in progress and the weight of the call are looked
+    /// up in the chain
+    Multisig {
+        threshold: u16,
+        other_signatories: Vec<Vec<u8>>,
+    },
+    /// Any call that takes the call as one of its arguments like `Utility::as_derivative`,
+    /// the rest of its arguments are given by name in a JSON object
+    Dispatch { path: String, args: JsonValue },
+}
+
+/// A call dispatched through another call that wraps it
+#[derive(Clone, Debug)]
+pub struct Wrapped<C> {
+    wrapper: Wrapper,
+    call: C,
+}
+
+impl<C> Wrapped<C> {
+    pub fn new(wrapper: Wrapper, call: C) -> Self {
+        Wrapped { wrapper, call }
+    }
+}
+
+impl<C: Call> Call for Wrapped<C> {
+    async fn encode_call(
+        &self,
+        chain: &impl Backend,
+        meta: &Metadata,
+        path: &str,
+        sender: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let call = self.call.encode_call(chain, meta, path, sender).await?;
+
+        let (path, args) = match &self.wrapper {
+            Wrapper::Proxy {
+                real,
+                force_proxy_type,
+            } => {
+                let force_proxy_type = force_proxy_type.clone().map_or(JsonValue::Null, Into::into);
+                let args = BTreeMap::from([
+                    ("real", Arg::Account(real.clone())),
+                    ("force_proxy_type", Arg::Value(force_proxy_type)),
+                ]);
+                ("proxy/proxy", args)
+            }
+            Wrapper::Multisig {
+                threshold,
+                other_signatories,
+            } => {
+                let sender = sender.ok_or(Error::BadInput)?;
+                let mut others = other_signatories
+                    .iter()
+                    .filter(|s| s.as_slice() != sender)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                others.sort();
+                others.dedup();
+
+                let timepoint =
+                    multisig_timepoint(chain, meta, sender, &others, *threshold, &call).await?;
+                let weight = call_weight(chain, meta, &call).await?;
+
+                let others = [Compact(others.len() as u32).encode(), others.concat()].concat();
+                let args = BTreeMap::from([
+                    ("threshold", Arg::Value((*threshold).into())),
+                    ("other_signatories", Arg::Encoded(others)),
+                    ("maybe_timepoint", Arg::Value(timepoint)),
+                    ("max_weight", Arg::Value(weight)),
+                ]);
+                ("multisig/as_multi", args)
+            }
+            Wrapper::Dispatch { path, args } => {
+                let JsonValue::Object(args) = args else {
+                    return Err(Error::BadInput);
+                };
+                let args = args
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), Arg::Value(value.clone())))
+                    .collect();
+                (path.as_str(), args)
+            }
+        };
+
+        encode_call_with(meta, path, args, Some(&call))
+    }
+}
+
+/// Account of the multisig of the sender and the other signatories as
+/// `pallet_multisig` derives it, it has the length of the sender's account
+pub fn multisig_account(sender: &[u8], others: &[Vec<u8>], threshold: u16) -> Vec<u8> {
+    let mut signatories = others.to_vec();
+    signatories.push(sender.to_vec());
+    signatories.sort();
+
+    let entropy = [
+        &b"modlpy/utilisuba"[..],
+        &Compact(signatories.len() as u32).encode(),
+        &signatories.concat(),
+        &threshold.encode(),
+    ]
+    .concat();
+    let mut account = hash(&Hasher::Blake2_256, entropy);
+    account.resize(sender.len(), 0);
+    account
+}
+
+/// Timepoint of the approval of the call already in progress, `null` when the
+/// sender is the first to approve it
+async fn multisig_timepoint(
+    chain: &impl Backend,
+    meta: &Metadata,
+    sender: &[u8],
+    others: &[Vec<u8>],
+    threshold: u16,
+    call: &[u8],
+) -> Result<JsonValue> {
+    let account = multisig_account(sender, others, threshold);
+    let call_hash = hash(&Hasher::Blake2_256, call);
+    let path = format!(
+        "multisig/multisigs/0x{}/0x{}",
+        hex::encode(account),
+        hex::encode(call_hash)
+    );
+
+    Ok(
+        match crate::query(chain, meta, &path, None, 0, None).await? {
+            Response::Value(multisig) => JsonValue::from(multisig)
+                .get("when")
+                .cloned()
+                .ok_or(Error::BadMetadata)?,
+            _ => JsonValue::Null,
+        },
+    )
+}
+
+/// Weight of the call the last approval of a multisig dispatches at most
+async fn call_weight(chain: &impl Backend, meta: &Metadata, call: &[u8]) -> Result<JsonValue> {
+    let len = u32::try_from(call.len()).expect("call size expected to be <4GB");
+    let args = [call, &len.encode()].concat();
+    let info: FeeInfo = crate::payment_api(
+        chain,
+        meta,
+        "TransactionPaymentCallApi",
+        "query_call_info",
+        &args,
+    )
+    .await?;
+
+    serde_json::to_value(info.weight).map_err(|e| Error::Encode(e.to_string()))
+}
@@ -0,0 +1,823 @@
+/*!
+A client keeps the connections to the chains sube talks to along with their
+metadata so they can be reused by subsequent queries and extrinsics.
+
+Chains are identified by the scheme, host and port of their url, once the
+client reaches its capacity the least recently used chain is evicted. The
+runtime version of the chains is followed, with a subscription when the
+backend supports it, to refresh their metadata after a runtime upgrade.
+Responses share the metadata they were decoded with, the metadata of evicted
+chains or replaced after an upgrade is freed once no response uses it.
+
+A chain can also be reached through several endpoints, requests are then
+spread over them and sent to another one when an endpoint is down.
+
+For tests chains can be replaced by mock backends, and the requests to real
+chains can be recorded to files that are replayed later on.
+*/
+use alloc::sync::Arc;
+use codec::Decode;
+use core::time::Duration;
+use no_std_async::Mutex;
+use url::Url;
+
+#[cfg(feature = "std")]
+use crate::cache::MetadataCache;
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+use crate::failover::{self, Failover};
+#[cfg(any(feature = "http", feature = "http-web"))]
+use crate::http::Backend as HttpBackend;
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+use crate::mock::{Recorder, Recording, Replayer};
+#[cfg(any(feature = "http", feature = "http-web", feature = "ws", feature = "js"))]
+use crate::rpc::RpcClient;
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+use crate::rpc::{Rpc, RpcResult};
+use crate::{
+    meta::BlockInfo, prelude::*, Backend, ConfirmationLevel, Error, Metadata, MockBackend, RawKey,
+    RawValue, Result, TxInfo,
+};
+#[cfg(feature = "ws")]
+use crate::{rpc::Subscription, ws::Backend as WSBackend};
+
+/// Number of chains a client keeps by default
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// How long the runtime version of a chain is trusted by default before asking
+/// for it again, when the backend can't notify about runtime upgrades
+pub const DEFAULT_VERSION_TTL: Duration = Duration::from_secs(60);
+
+static DEFAULT_CLIENT: async_once_cell::OnceCell<SubeClient> = async_once_cell::OnceCell::new();
+
+/// The client used by builders that are not given one
+pub(crate) async fn default_client() -> &'static SubeClient {
+    DEFAULT_CLIENT
+        .get_or_init(async { SubeClient::default() })
+        .await
+}
+
+/// Pool of chain backends and their metadata
+pub struct SubeClient {
+    capacity: usize,
+    version_ttl: Option<Duration>,
+    // least recently used chains first
+    chains: Mutex<Vec<Chain>>,
+    #[cfg(feature = "std")]
+    cache: Option<MetadataCache>,
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    failover: failover::Options,
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    recording: Option<Recording>,
+    // backends used instead of connecting to the chains with the given ids
+    mocks: Vec<(String, MockBackend)>,
+}
+
+impl Default for SubeClient {
+    fn default() -> Self {
+        SubeClient {
+            capacity: DEFAULT_CAPACITY,
+            version_ttl: Some(DEFAULT_VERSION_TTL),
+            chains: Mutex::new(Vec::new()),
+            #[cfg(feature = "std")]
+            cache: None,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            failover: failover::Options::default(),
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            recording: None,
+            mocks: Vec::new(),
+        }
+    }
+}
+
+impl SubeClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of chains to keep, at least one chain is always kept
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        Self { capacity, ..self }
+    }
+
+    /// How long the runtime version of chains whose backend can't notify about
+    /// runtime upgrades is trusted before asking for it again, [`DEFAULT_VERSION_TTL`]
+    /// by default. With `None` the metadata of those chains is never refreshed.
+    /// Without `std` there is no clock and the version is asked every time
+    pub fn with_version_check(self, ttl: Option<Duration>) -> Self {
+        Self {
+            version_ttl: ttl,
+            ..self
+        }
+    }
+
+    /// Store the metadata of the chains in the given cache and load it from there
+    /// as long as the runtime version of the chain doesn't change
+    #[cfg(feature = "std")]
+    pub fn with_metadata_cache(self, cache: MetadataCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// How requests are spread over the endpoints of chains reached through
+    /// several of them
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    pub fn with_failover_options(self, options: failover::Options) -> Self {
+        Self {
+            failover: options,
+            ..self
+        }
+    }
+
+    /// Record the requests made to the chains to files in a directory or answer
+    /// them with the responses recorded there instead of connecting to the chains
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    pub fn with_recording(self, recording: Recording) -> Self {
+        Self {
+            recording: Some(recording),
+            ..self
+        }
+    }
+
+    /// Use the mock backend for the chain at the given url instead of connecting to it
+    pub fn with_mock(mut self, url: &str, backend: MockBackend) -> Result<Self> {
+        let id = chain_id(&[crate::builder::chain_string_to_url(url)?])?;
+        self.mocks.retain(|(m, _)| *m != id);
+        self.mocks.push((id, backend));
+        Ok(self)
+    }
+
+    /// Backend and metadata of the chain at the given endpoints, connecting to it
+    /// the first time or when it was evicted. The provided metadata is used instead
+    /// of the chain's one when connecting
+    ///
+    /// The pool is only locked to look up and update its chains, connecting and
+    /// asking for the runtime version happen while other chains can be used.
+    pub(crate) async fn chain(
+        &self,
+        urls: &[Url],
+        metadata: Option<Metadata>,
+    ) -> Result<(Arc<AnyBackend>, Arc<Metadata>)> {
+        let id = chain_id(urls)?;
+
+        let cached = {
+            let mut chains = self.chains.lock().await;
+            chains.iter().position(|c| c.id == id).map(|i| {
+                let mut chain = chains.remove(i);
+                let poll = !chain.follow_upgrades() && chain.version_expired(self.version_ttl);
+                let cached = (
+                    chain.backend.clone(),
+                    chain.meta.clone(),
+                    chain.spec_version,
+                    chain.latest_version,
+                    poll,
+                );
+                chains.push(chain);
+                cached
+            })
+        };
+
+        let Some((backend, meta, spec_version, latest_version, poll)) = cached else {
+            log::trace!("connecting to {}", id);
+            let chain = self.connect(id, urls, metadata).await?;
+            return Ok(self.add(chain).await);
+        };
+
+        let latest_version = match poll {
+            true => runtime_version(&backend).await.or(latest_version),
+            false => latest_version,
+        };
+        let Some(version) = latest_version.filter(|v| spec_version != Some(*v)) else {
+            return Ok((backend, meta));
+        };
+
+        log::info!("{} runtime upgraded to version {}", id, version);
+        let meta = Arc::new(self.load_metadata(&backend, Some(version)).await?);
+        let mut chains = self.chains.lock().await;
+        if let Some(chain) = chains
+            .iter_mut()
+            .find(|c| Arc::ptr_eq(&c.backend, &backend))
+        {
+            chain.meta = meta.clone();
+            chain.spec_version = Some(version);
+            chain.latest_version = chain.latest_version.max(Some(version));
+        }
+        Ok((backend, meta))
+    }
+
+    // Add a chain that was just connected evicting the least recently used one if
+    // the client is full, a chain connected meanwhile with the same id is kept instead
+    async fn add(&self, chain: Chain) -> (Arc<AnyBackend>, Arc<Metadata>) {
+        let mut chains = self.chains.lock().await;
+        match chains.iter().position(|c| c.id == chain.id) {
+            Some(i) => {
+                let existing = chains.remove(i);
+                chains.push(existing);
+            }
+            None => {
+                if chains.len() >= self.capacity.max(1) {
+                    let evicted = chains.remove(0);
+                    log::debug!("evicting {}", evicted.id);
+                }
+                chains.push(chain);
+            }
+        }
+        let chain = chains.last().expect("chain was just added");
+        (chain.backend.clone(), chain.meta.clone())
+    }
+}
+
+// Chains are identified by the scheme, host and port of their endpoints
+fn chain_id(urls: &[Url]) -> Result<String> {
+    Ok(urls
+        .iter()
+        .map(|url| {
+            Ok(format!(
+                "{}://{}:{}",
+                url.scheme(),
+                url.host_str().ok_or(Error::BadInput)?,
+                url.port_or_known_default().unwrap_or(80)
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .join(","))
+}
+
+impl SubeClient {
+    async fn connect(&self, id: String, urls: &[Url], metadata: Option<Metadata>) -> Result<Chain> {
+        let backend = match self.mocks.iter().find(|(m, _)| *m == id) {
+            Some((_, mock)) => AnyBackend::Mock(mock.clone()),
+            None => self.backend(&id, urls).await?,
+        };
+        let spec_version = runtime_version(&backend).await;
+        let meta = match metadata {
+            Some(m) => m,
+            None => self.load_metadata(&backend, spec_version).await?,
+        };
+        #[cfg(feature = "ws")]
+        let versions = match &backend {
+            AnyBackend::Ws(b) => b.subscribe_runtime_version().await.ok(),
+            #[cfg(feature = "std")]
+            AnyBackend::Rpc(b) => b.subscribe_runtime_version().await.ok(),
+            _ => None,
+        };
+
+        Ok(Chain {
+            id,
+            backend: Arc::new(backend),
+            meta: Arc::new(meta),
+            spec_version,
+            latest_version: spec_version,
+            #[cfg(feature = "std")]
+            checked_at: std::time::Instant::now(),
+            #[cfg(feature = "ws")]
+            versions,
+        })
+    }
+
+    #[cfg_attr(
+        not(all(feature = "std", any(feature = "http", feature = "ws"))),
+        allow(unused_variables)
+    )]
+    async fn backend(&self, id: &str, urls: &[Url]) -> Result<AnyBackend> {
+        #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+        {
+            let rpc = match &self.recording {
+                Some(r @ Recording::Replay(_)) => {
+                    let replayer = Replayer::load(r.file(id))
+                        .map_err(|e| Error::Platform(format!("can't replay {}: {}", id, e)))?;
+                    Some(AnyRpc::Replay(replayer))
+                }
+                Some(r @ Recording::Record(_)) => {
+                    let failover = get_failover(urls, &self.failover).await?;
+                    let recorder = Recorder::new(failover, r.file(id))
+                        .map_err(|e| Error::Platform(format!("can't record {}: {}", id, e)))?;
+                    Some(AnyRpc::Record(recorder))
+                }
+                None if urls.len() > 1 => {
+                    Some(AnyRpc::Failover(get_failover(urls, &self.failover).await?))
+                }
+                None => None,
+            };
+            if let Some(rpc) = rpc {
+                return Ok(AnyBackend::Rpc(RpcClient(rpc)));
+            }
+        }
+
+        match urls {
+            [url] => get_backend_by_url(url.clone()).await,
+            _ => Err(Error::BadInput),
+        }
+    }
+
+    // Metadata of the given runtime version is taken from the cache if there is one
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    async fn load_metadata(
+        &self,
+        backend: &AnyBackend,
+        spec_version: Option<u32>,
+    ) -> Result<Metadata> {
+        #[cfg(feature = "std")]
+        if let (Some(cache), Some(version)) = (&self.cache, spec_version) {
+            let genesis = backend.block_info(Some(0)).await?.hash;
+            if let Some(meta) = cache.get(&genesis, version) {
+                log::debug!("using cached metadata of runtime version {}", version);
+                return Ok(meta);
+            }
+            let meta = backend.metadata().await.map_err(|_| Error::BadMetadata)?;
+            if let Err(err) = cache.set(&genesis, version, &meta) {
+                log::warn!("can't cache the metadata: {}", err);
+            }
+            return Ok(meta);
+        }
+
+        backend.metadata().await.map_err(|_| Error::BadMetadata)
+    }
+}
+
+struct Chain {
+    id: String,
+    backend: Arc<AnyBackend>,
+    meta: Arc<Metadata>,
+    // runtime version the metadata belongs to and the latest one known
+    spec_version: Option<u32>,
+    latest_version: Option<u32>,
+    // when the runtime version was last asked for
+    #[cfg(feature = "std")]
+    checked_at: std::time::Instant,
+    // notifications of runtime upgrades
+    #[cfg(feature = "ws")]
+    versions: Option<Subscription>,
+}
+
+impl Chain {
+    /// Take the runtime upgrades notified since the chain was last used,
+    /// `false` when the backend can't notify about them
+    fn follow_upgrades(&mut self) -> bool {
+        #[cfg(feature = "ws")]
+        if let Some(versions) = self.versions.as_mut() {
+            use futures_util::{FutureExt, StreamExt};
+            while let Some(notification) = versions.next().now_or_never() {
+                let Some(version) = notification else {
+                    log::debug!("runtime version subscription of {} closed", self.id);
+                    self.versions = None;
+                    return false;
+                };
+                if let Some(v) = version["specVersion"].as_u64() {
+                    self.latest_version = Some(v as u32);
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Whether the runtime version has to be asked for again,
+    /// it's considered asked from then on
+    fn version_expired(&mut self, ttl: Option<Duration>) -> bool {
+        let Some(ttl) = ttl else {
+            return false;
+        };
+        #[cfg(feature = "std")]
+        {
+            if self.checked_at.elapsed() < ttl {
+                return false;
+            }
+            self.checked_at = std::time::Instant::now();
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = ttl;
+        true
+    }
+}
+
+// The spec version as reported by the `Core_version` runtime API
+async fn runtime_version(backend: &AnyBackend) -> Option<u32> {
+    let version = backend
+        .runtime_call("Core_version", &[], None)
+        .await
+        .map_err(|e| log::debug!("can't get the runtime version {:?}", e))
+        .ok()?;
+    // spec and implementation names precede the authoring and spec versions
+    let (_, _, _, spec_version) =
+        <(String, String, u32, u32)>::decode(&mut version.as_slice()).ok()?;
+    Some(spec_version)
+}
+
+async fn get_backend_by_url(url: Url) -> Result<AnyBackend> {
+    match url.scheme() {
+        #[cfg(feature = "ws")]
+        "ws" | "wss" => Ok(AnyBackend::Ws(RpcClient(
+            WSBackend::new_ws2(url.to_string().as_str()).await?,
+        ))),
+        #[cfg(any(feature = "http", feature = "http-web"))]
+        "http" | "https" => Ok(AnyBackend::Http(RpcClient(HttpBackend::new(url)))),
+        _ => Err(Error::BadInput),
+    }
+}
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+async fn get_transport_by_url(url: Url) -> Result<Transport> {
+    match url.scheme() {
+        #[cfg(feature = "ws")]
+        "ws" | "wss" => Ok(Transport::Ws(WSBackend::new_ws2(url.as_str()).await?)),
+        #[cfg(feature = "http")]
+        "http" | "https" => Ok(Transport::Http(HttpBackend::new(url))),
+        _ => Err(Error::BadInput),
+    }
+}
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+async fn get_failover(urls: &[Url], options: &failover::Options) -> Result<Failover<Transport>> {
+    let mut endpoints = Vec::with_capacity(urls.len());
+    for url in urls {
+        endpoints.push(get_transport_by_url(url.clone()).await?);
+    }
+    Failover::new(endpoints, options.clone()).await
+}
+
+/// Endpoints of a failover backend can use different transports
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+pub(crate) enum Transport {
+    #[cfg(feature = "http")]
+    Http(HttpBackend),
+    #[cfg(feature = "ws")]
+    Ws(WSBackend),
+}
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+impl Rpc for Transport {
+    async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        match self {
+            #[cfg(feature = "http")]
+            Transport::Http(r) => r.rpc(method, params).await,
+            #[cfg(feature = "ws")]
+            Transport::Ws(r) => r.rpc(method, params).await,
+        }
+    }
+
+    async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        match self {
+            #[cfg(feature = "http")]
+            Transport::Http(r) => r.rpc_batch(requests).await,
+            #[cfg(feature = "ws")]
+            Transport::Ws(r) => r.rpc_batch(requests).await,
+        }
+    }
+
+    #[cfg(feature = "ws")]
+    async fn subscribe(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> RpcResult<Subscription> {
+        match self {
+            #[cfg(feature = "http")]
+            Transport::Http(r) => r.subscribe(method, params, unsubscribe).await,
+            Transport::Ws(r) => r.subscribe(method, params, unsubscribe).await,
+        }
+    }
+}
+
+/// JSONRpc backends that wrap the ones of the endpoints of a chain
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+pub(crate) enum AnyRpc {
+    Failover(Failover<Transport>),
+    Record(Recorder<Failover<Transport>>),
+    Replay(Replayer),
+}
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+impl Rpc for AnyRpc {
+    async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        match self {
+            AnyRpc::Failover(r) => r.rpc(method, params).await,
+            AnyRpc::Record(r) => r.rpc(method, params).await,
+            AnyRpc::Replay(r) => r.rpc(method, params).await,
+        }
+    }
+
+    async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        match self {
+            AnyRpc::Failover(r) => r.rpc_batch(requests).await,
+            AnyRpc::Record(r) => r.rpc_batch(requests).await,
+            AnyRpc::Replay(r) => r.rpc_batch(requests).await,
+        }
+    }
+
+    #[cfg(feature = "ws")]
+    async fn subscribe(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> RpcResult<Subscription> {
+        match self {
+            AnyRpc::Failover(r) => r.subscribe(method, params, unsubscribe).await,
+            AnyRpc::Record(r) => r.subscribe(method, params, unsubscribe).await,
+            AnyRpc::Replay(r) => r.subscribe(method, params, unsubscribe).await,
+        }
+    }
+}
+
+pub(crate) enum AnyBackend {
+    #[cfg(any(feature = "http", feature = "http-web"))]
+    Http(RpcClient<HttpBackend>),
+    #[cfg(feature = "ws")]
+    Ws(RpcClient<WSBackend>),
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    Rpc(RpcClient<AnyRpc>),
+    Mock(MockBackend),
+}
+
+impl Backend for &AnyBackend {
+    async fn get_storage_items(
+        &self,
+        keys: Vec<RawKey>,
+        block: Option<u32>,
+    ) -> Result<impl Iterator<Item = (RawKey, Option<RawValue>)>> {
+        let result: Box<dyn Iterator<Item = (RawKey, Option<RawValue>)>> = match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => Box::new(b.get_storage_items(keys, block).await?),
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => Box::new(b.get_storage_items(keys, block).await?),
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => Box::new(b.get_storage_items(keys, block).await?),
+            AnyBackend::Mock(b) => Box::new(b.get_storage_items(keys, block).await?),
+        };
+
+        Ok(result)
+    }
+
+    async fn get_storage_item(&self, key: RawKey, block: Option<u32>) -> Result<Option<Vec<u8>>> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.get_storage_item(key, block).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.get_storage_item(key, block).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.get_storage_item(key, block).await,
+            AnyBackend::Mock(b) => b.get_storage_item(key, block).await,
+        }
+    }
+
+    async fn get_keys_paged(
+        &self,
+        prefix: RawKey,
+        size: u16,
+        start_key: Option<RawKey>,
+        block: Option<u32>,
+    ) -> Result<Vec<RawKey>> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.get_keys_paged(prefix, size, start_key, block).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.get_keys_paged(prefix, size, start_key, block).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.get_keys_paged(prefix, size, start_key, block).await,
+            AnyBackend::Mock(b) => b.get_keys_paged(prefix, size, start_key, block).await,
+        }
+    }
+
+    async fn metadata(&self) -> Result<Metadata> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.metadata().await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.metadata().await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.metadata().await,
+            AnyBackend::Mock(b) => b.metadata().await,
+        }
+    }
+
+    async fn submit(&self, ext: impl AsRef<[u8]>) -> Result<()> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.submit(ext).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.submit(ext).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.submit(ext).await,
+            AnyBackend::Mock(b) => b.submit(ext).await,
+        }
+    }
+
+    async fn submit_and_watch(
+        &self,
+        ext: impl AsRef<[u8]>,
+        level: ConfirmationLevel,
+    ) -> Result<TxInfo> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.submit_and_watch(ext, level).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.submit_and_watch(ext, level).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.submit_and_watch(ext, level).await,
+            AnyBackend::Mock(b) => b.submit_and_watch(ext, level).await,
+        }
+    }
+
+    async fn block_info(&self, at: Option<u32>) -> Result<BlockInfo> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.block_info(at).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.block_info(at).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.block_info(at).await,
+            AnyBackend::Mock(b) => b.block_info(at).await,
+        }
+    }
+
+    async fn runtime_call(&self, method: &str, args: &[u8], block: Option<u32>) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.runtime_call(method, args, block).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.runtime_call(method, args, block).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.runtime_call(method, args, block).await,
+            AnyBackend::Mock(b) => b.runtime_call(method, args, block).await,
+        }
+    }
+
+    async fn block_extrinsics(&self, at: Option<u32>) -> Result<Vec<Vec<u8>>> {
+        match self {
+            #[cfg(any(feature = "http", feature = "http-web"))]
+            AnyBackend::Http(b) => b.block_extrinsics(at).await,
+            #[cfg(feature = "ws")]
+            AnyBackend::Ws(b) => b.block_extrinsics(at).await,
+            #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+            AnyBackend::Rpc(b) => b.block_extrinsics(at).await,
+            AnyBackend::Mock(b) => b.block_extrinsics(at).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::fixtures::kreivo;
+    use codec::Encode;
+
+    // a chain whose runtime reports the given spec version
+    fn mock(spec_version: u32) -> MockBackend {
+        let version = ("kreivo", "kreivo", 1u32, spec_version).encode();
+        MockBackend::new(kreivo()).with_runtime_call("Core_version", version)
+    }
+
+    fn urls(host: &str) -> Vec<Url> {
+        vec![Url::parse(&format!("wss://{}", host)).unwrap()]
+    }
+
+    // pretend the metadata was loaded before the runtime was upgraded
+    async fn loaded_with(client: &SubeClient, spec_version: u32) {
+        let chain = &mut client.chains.lock().await[0];
+        chain.spec_version = Some(spec_version);
+        chain.latest_version = Some(spec_version);
+    }
+
+    async fn ids(client: &SubeClient) -> Vec<String> {
+        client
+            .chains
+            .lock()
+            .await
+            .iter()
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn evict_the_least_recently_used_chain() {
+        let client = SubeClient::new()
+            .with_capacity(2)
+            .with_mock("a.mock", mock(1))
+            .and_then(|c| c.with_mock("b.mock", mock(1)))
+            .and_then(|c| c.with_mock("c.mock", mock(1)))
+            .unwrap();
+        let client = &client;
+        let meta = |host| async move { client.chain(&urls(host), None).await.unwrap().1 };
+
+        let a = meta("a.mock").await;
+        let b = meta("b.mock").await;
+        assert!(Arc::ptr_eq(&a, &meta("a.mock").await));
+        assert_eq!(ids(client).await, ["wss://b.mock:443", "wss://a.mock:443"]);
+
+        // b is the least recently used chain, its metadata lives as long as it's used
+        let evicted = Arc::downgrade(&b);
+        meta("c.mock").await;
+        assert_eq!(ids(client).await, ["wss://a.mock:443", "wss://c.mock:443"]);
+        assert!(evicted.upgrade().is_some());
+        drop(b);
+        assert!(evicted.upgrade().is_none());
+
+        // evicted chains are connected again
+        assert!(Arc::ptr_eq(&a, &meta("a.mock").await));
+        meta("b.mock").await;
+        assert_eq!(ids(client).await, ["wss://a.mock:443", "wss://b.mock:443"]);
+    }
+
+    #[async_std::test]
+    async fn refresh_the_metadata_after_a_runtime_upgrade() {
+        let client = SubeClient::new()
+            .with_version_check(Some(Duration::ZERO))
+            .with_mock("kreivo.mock", mock(2))
+            .unwrap();
+        let urls = urls("kreivo.mock");
+        let (_, meta) = client.chain(&urls, None).await.unwrap();
+        assert!(Arc::ptr_eq(
+            &meta,
+            &client.chain(&urls, None).await.unwrap().1
+        ));
+
+        loaded_with(&client, 1).await;
+        let (_, upgraded) = client.chain(&urls, None).await.unwrap();
+        assert!(!Arc::ptr_eq(&meta, &upgraded));
+        assert_eq!(client.chains.lock().await[0].spec_version, Some(2));
+        assert_eq!(Arc::strong_count(&meta), 1);
+        assert!(Arc::ptr_eq(
+            &upgraded,
+            &client.chain(&urls, None).await.unwrap().1
+        ));
+    }
+
+    #[async_std::test]
+    async fn ask_for_the_runtime_version_once_it_expires() {
+        let client = SubeClient::new().with_mock("kreivo.mock", mock(2)).unwrap();
+        let urls = urls("kreivo.mock");
+        let (_, meta) = client.chain(&urls, None).await.unwrap();
+
+        // the version the metadata was loaded with is trusted for a while
+        loaded_with(&client, 1).await;
+        #[cfg(feature = "std")]
+        {
+            assert!(Arc::ptr_eq(
+                &meta,
+                &client.chain(&urls, None).await.unwrap().1
+            ));
+            let expired = std::time::Instant::now().checked_sub(DEFAULT_VERSION_TTL);
+            client.chains.lock().await[0].checked_at = expired.unwrap();
+        }
+        let (_, upgraded) = client.chain(&urls, None).await.unwrap();
+        assert!(!Arc::ptr_eq(&meta, &upgraded));
+
+        // or forever when the check is disabled
+        let client = SubeClient::new()
+            .with_version_check(None)
+            .with_mock("kreivo.mock", mock(2))
+            .unwrap();
+        let (_, meta) = client.chain(&urls, None).await.unwrap();
+        loaded_with(&client, 1).await;
+        assert!(Arc::ptr_eq(
+            &meta,
+            &client.chain(&urls, None).await.unwrap().1
+        ));
+    }
+
+    #[cfg(feature = "ws")]
+    #[async_std::test]
+    async fn follow_the_notified_runtime_upgrades() {
+        let client = SubeClient::new().with_mock("kreivo.mock", mock(1)).unwrap();
+        let urls = urls("kreivo.mock");
+        let (_, meta) = client.chain(&urls, None).await.unwrap();
+        let (tx, rx) = futures_channel::mpsc::unbounded();
+        client.chains.lock().await[0].versions = Some(Subscription::new(rx, || {}));
+        assert!(Arc::ptr_eq(
+            &meta,
+            &client.chain(&urls, None).await.unwrap().1
+        ));
+
+        tx.unbounded_send(serde_json::json!({ "specVersion": 2 }))
+            .unwrap();
+        let (_, upgraded) = client.chain(&urls, None).await.unwrap();
+        assert!(!Arc::ptr_eq(&meta, &upgraded));
+        assert_eq!(client.chains.lock().await[0].spec_version, Some(2));
+
+        // a closed subscription is replaced by asking for the version
+        drop(tx);
+        client.chain(&urls, None).await.unwrap();
+        assert!(client.chains.lock().await[0].versions.is_none());
+    }
+
+    #[test]
+    fn mocks_of_invalid_urls() {
+        let res = SubeClient::new().with_mock("wss://", mock(1));
+        assert!(res.is_err());
+    }
+}
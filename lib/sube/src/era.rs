@@ -0,0 +1,80 @@
+use codec::{Encode, Output};
+
+/// The era of a transaction, it defines the range of blocks in which
+/// a transaction is valid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Era {
+    /// The transaction is valid forever
+    Immortal,
+    /// Period and phase, the transaction is valid for `period` blocks
+    /// starting at the block whose number modulo `period` equals `phase`
+    Mortal(u64, u64),
+}
+
+impl Era {
+    /// Create a mortal era that starts at the `current` block and lasts
+    /// `period` blocks, the period is rounded to the next power of two
+    /// and clamped between 4 and 65536.
+    pub fn mortal(period: u64, current: u64) -> Self {
+        let period = period
+            .checked_next_power_of_two()
+            .unwrap_or(1 << 16)
+            .clamp(4, 1 << 16);
+        let phase = current % period;
+        let quantize_factor = (period >> 12).max(1);
+        let quantized_phase = phase / quantize_factor * quantize_factor;
+
+        Era::Mortal(period, quantized_phase)
+    }
+
+    /// The block number the era starts at, its hash is the checkpoint
+    /// the transaction is signed against.
+    pub fn birth(self, current: u64) -> u64 {
+        match self {
+            Era::Immortal => 0,
+            Era::Mortal(period, phase) => (current.max(phase) - phase) / period * period + phase,
+        }
+    }
+}
+
+impl Encode for Era {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            Era::Immortal => dest.push_byte(0),
+            Era::Mortal(period, phase) => {
+                let quantize_factor = (*period >> 12).max(1);
+                let encoded = (period.trailing_zeros() - 1).clamp(1, 15) as u16
+                    | ((phase / quantize_factor) << 4) as u16;
+                encoded.encode_to(dest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_immortal() {
+        assert_eq!(Era::Immortal.encode(), vec![0]);
+    }
+
+    #[test]
+    fn encode_mortal() {
+        let era = Era::mortal(64, 42);
+        assert_eq!(era, Era::Mortal(64, 42));
+        assert_eq!(era.encode(), vec![5 + 42 % 16 * 16, 42 / 16]);
+        assert_eq!(era.birth(42), 42);
+    }
+
+    #[test]
+    fn encode_quantized_mortal() {
+        let era = Era::mortal(32768, 20_000);
+        assert_eq!(era, Era::Mortal(32768, 20_000));
+        assert_eq!(
+            era.encode(),
+            vec![(14 + 2500 % 16 * 16) as u8, (2500 / 16) as u8]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+/*!
+Events emitted by the runtime are stored in the `System::Events` storage item
+of every block. Each event record carries the phase of the block execution it
+was emitted at, which ties it to the extrinsic that triggered it and allows
+finding out if a submitted extrinsic was dispatched successfully.
+*/
+use core::fmt;
+use scale_info::TypeDef;
+use serde::{Deserialize, Serialize};
+
+use crate::meta_ext::{Meta, Pallet as _, StorageKey};
+use crate::prelude::*;
+use crate::{Backend, Error, JsonValue, Result, Value};
+
+type TypeId = u32;
+
+/// The moment of the block execution an event was emitted at
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Applying the extrinsic with the given index in the block
+    ApplyExtrinsic(u32),
+    Finalization,
+    Initialization,
+}
+
+/// An event emitted by the runtime in its JSON form
+#[derive(Serialize, Debug, Clone)]
+pub struct EventRecord {
+    pub phase: Phase,
+    pub pallet: String,
+    pub name: String,
+    /// Fields of the event, `null` for events without data
+    pub data: JsonValue,
+    pub topics: Vec<String>,
+}
+
+impl EventRecord {
+    /// Index of the extrinsic that emitted the event
+    pub fn extrinsic(&self) -> Option<u32> {
+        match self.phase {
+            Phase::ApplyExtrinsic(idx) => Some(idx),
+            _ => None,
+        }
+    }
+
+    pub fn is(&self, pallet: &str, name: &str) -> bool {
+        self.pallet == pallet && self.name == name
+    }
+
+    /// The reason an extrinsic failed when this is a `System::ExtrinsicFailed` event
+    pub fn dispatch_error(&self, meta: &impl Meta) -> Option<DispatchError> {
+        if !self.is("System", "ExtrinsicFailed") {
+            return None;
+        }
+        // older runtimes declare the event fields without names
+        let err = self
+            .data
+            .get("dispatch_error")
+            .or_else(|| self.data.get(0))?;
+        Some(DispatchError::resolve(meta, err.clone()))
+    }
+
+    fn from_json(mut record: JsonValue) -> Result<Self> {
+        let bad_record = || Error::Decode("unexpected event record".into());
+        if !record.is_object() {
+            return Err(bad_record());
+        }
+
+        let phase = serde_json::from_value(record["phase"].take()).map_err(|_| bad_record())?;
+        let (pallet, event) = variant(record["event"].take()).ok_or_else(bad_record)?;
+        let (name, data) = variant(event).ok_or_else(bad_record)?;
+        let topics = match record["topics"].take() {
+            JsonValue::Array(topics) => topics.iter().filter_map(to_hex).collect(),
+            _ => vec![],
+        };
+
+        Ok(EventRecord {
+            phase,
+            pallet,
+            name,
+            data,
+            topics,
+        })
+    }
+}
+
+/// Error of a failed extrinsic
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum DispatchError {
+    /// An error declared by a pallet resolved to its name
+    Module { pallet: String, error: String },
+    /// Any other kind of error like `BadOrigin` in its JSON form
+    Other(JsonValue),
+}
+
+impl DispatchError {
+    fn resolve(meta: &impl Meta, err: JsonValue) -> Self {
+        let module = err.get("Module").and_then(|m| {
+            let index = m.get("index")?.as_u64()?;
+            // the error is a single byte in older runtimes and 4 bytes in newer ones
+            let error = match m.get("error")? {
+                JsonValue::Array(bytes) => bytes.first()?.as_u64()?,
+                error => error.as_u64()?,
+            };
+            let pallet = meta.pallets().find(|p| u64::from(p.index()) == index)?;
+            let ty = meta.registry().resolve(pallet.error()?.ty.id)?;
+            let TypeDef::Variant(errors) = &ty.type_def else {
+                return None;
+            };
+            let error = errors
+                .variants
+                .iter()
+                .find(|v| u64::from(v.index) == error)?;
+
+            Some(DispatchError::Module {
+                pallet: pallet.name().into(),
+                error: error.name.clone(),
+            })
+        });
+
+        module.unwrap_or(DispatchError::Other(err))
+    }
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Module { pallet, error } => write!(f, "{}::{}", pallet, error),
+            Self::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Fetch the events emitted at the given block or the latest one
+pub async fn events(
+    chain: &impl Backend,
+    meta: &impl Meta,
+    block: Option<u32>,
+) -> Result<Vec<EventRecord>> {
+    let system = meta
+        .pallet_by_name("System")
+        .ok_or(Error::PalletNotFound("System".into()))?;
+    let key = StorageKey::build_with_registry(meta.registry(), system, "Events", &[] as &[&str])?;
+
+    match chain.get_storage_item(key.key(), block).await? {
+        Some(data) => decode(meta, key.ty, data),
+        None => Ok(vec![]),
+    }
+}
+
+/// Decode the raw value of the `System::Events` storage item whose type is `ty`,
+/// a sequence of records that wrap the runtime's event type
+pub fn decode(meta: &impl Meta, ty: TypeId, data: Vec<u8>) -> Result<Vec<EventRecord>> {
+    let records: JsonValue = Value::new(data, ty, meta.registry()).into();
+    match records {
+        JsonValue::Array(records) => records.into_iter().map(EventRecord::from_json).collect(),
+        _ => Err(Error::Decode("events are expected to be a sequence".into())),
+    }
+}
+
+/// Events emitted while applying the extrinsic with the given index
+pub fn extrinsic_events(
+    events: &[EventRecord],
+    index: u32,
+) -> impl Iterator<Item = &EventRecord> + '_ {
+    events.iter().filter(move |e| e.extrinsic() == Some(index))
+}
+
+/// Whether the extrinsic with the given index was dispatched successfully,
+/// `None` when there are no dispatch events for it
+pub fn dispatch_result(
+    meta: &impl Meta,
+    events: &[EventRecord],
+    index: u32,
+) -> Option<core::result::Result<(), DispatchError>> {
+    extrinsic_events(events, index).find_map(|e| {
+        if e.is("System", "ExtrinsicSuccess") {
+            Some(Ok(()))
+        } else {
+            e.dispatch_error(meta).map(Err)
+        }
+    })
+}
+
+// Enums are serialized as a single entry object or as a string for unit variants
+fn variant(value: JsonValue) -> Option<(String, JsonValue)> {
+    match value {
+        JsonValue::String(name) => Some((name, JsonValue::Null)),
+        JsonValue::Object(v) if v.len() == 1 => v.into_iter().next(),
+        _ => None,
+    }
+}
+
+fn to_hex(bytes: &JsonValue) -> Option<String> {
+    let bytes = bytes
+        .as_array()?
+        .iter()
+        .map(|b| b.as_u64().map(|b| b as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    Some(format!("0x{}", hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{fixtures::kreivo, MockBackend};
+    use crate::Metadata;
+    use serde_json::json;
+
+    const ACCOUNT: [u8; 32] = [7; 32];
+    const TOPIC: [u8; 32] = [1; 32];
+
+    fn dispatch_info() -> JsonValue {
+        json!({
+            "weight": { "ref_time": 1000, "proof_size": 64 },
+            "class": "Normal",
+            "pays_fee": "Yes",
+        })
+    }
+
+    // a block with a successful extrinsic and a failed transfer
+    async fn block_events(meta: &Metadata) -> Vec<EventRecord> {
+        let balances = meta.pallet_by_name("Balances").unwrap().index();
+        let mut chain = MockBackend::new(meta.clone());
+        chain
+            .insert(
+                "system/events",
+                json!([
+                    {
+                        "phase": { "ApplyExtrinsic": 0 },
+                        "event": { "System": { "ExtrinsicSuccess": {
+                            "dispatch_info": dispatch_info(),
+                        }}},
+                        "topics": [],
+                    },
+                    {
+                        "phase": { "ApplyExtrinsic": 1 },
+                        "event": { "System": { "NewAccount": { "account": ACCOUNT } } },
+                        "topics": [TOPIC],
+                    },
+                    {
+                        "phase": { "ApplyExtrinsic": 1 },
+                        "event": { "System": { "ExtrinsicFailed": {
+                            "dispatch_error": { "Module": { "index": balances, "error": [2, 0, 0, 0] } },
+                            "dispatch_info": dispatch_info(),
+                        }}},
+                        "topics": [],
+                    },
+                    {
+                        "phase": "Finalization",
+                        "event": { "System": { "ExtrinsicFailed": {
+                            "dispatch_error": "BadOrigin",
+                            "dispatch_info": dispatch_info(),
+                        }}},
+                        "topics": [],
+                    },
+                ]),
+            )
+            .unwrap();
+        events(&chain, meta, None).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn decode_system_events() {
+        let meta = kreivo();
+        let events = block_events(&meta).await;
+
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].phase, Phase::ApplyExtrinsic(0));
+        assert!(events[0].is("System", "ExtrinsicSuccess"));
+        assert_eq!(
+            events[0].data["dispatch_info"]["weight"],
+            json!({ "ref_time": 1000, "proof_size": 64 })
+        );
+        assert_eq!(events[1].data, json!({ "account": ACCOUNT }));
+        assert_eq!(events[1].topics, [format!("0x{}", hex::encode(TOPIC))]);
+        assert_eq!(events[3].phase, Phase::Finalization);
+        assert_eq!(events[3].extrinsic(), None);
+
+        // no events stored for the block
+        let chain = MockBackend::new(meta.clone());
+        assert!(super::events(&chain, &meta, None).await.unwrap().is_empty());
+    }
+
+    #[async_std::test]
+    async fn events_of_an_extrinsic() {
+        let meta = kreivo();
+        let events = block_events(&meta).await;
+
+        let names = |index| {
+            extrinsic_events(&events, index)
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(0), ["ExtrinsicSuccess"]);
+        assert_eq!(names(1), ["NewAccount", "ExtrinsicFailed"]);
+        assert!(names(2).is_empty());
+
+        assert_eq!(dispatch_result(&meta, &events, 0), Some(Ok(())));
+        assert_eq!(dispatch_result(&meta, &events, 2), None);
+    }
+
+    #[async_std::test]
+    async fn resolve_module_errors() {
+        let meta = kreivo();
+        let events = block_events(&meta).await;
+
+        assert_eq!(
+            dispatch_result(&meta, &events, 1),
+            Some(Err(DispatchError::Module {
+                pallet: "Balances".into(),
+                error: "InsufficientBalance".into(),
+            }))
+        );
+        assert_eq!(
+            events[1].dispatch_error(&meta),
+            None,
+            "only failed extrinsics have a dispatch error"
+        );
+        let err = events[3].dispatch_error(&meta).unwrap();
+        assert_eq!(err, DispatchError::Other(json!("BadOrigin")));
+        assert_eq!(err.to_string(), "\"BadOrigin\"");
+
+        // errors of unknown pallets are left as they are
+        let err = json!({ "Module": { "index": 255, "error": [0, 0, 0, 0] } });
+        assert_eq!(
+            DispatchError::resolve(&meta, err.clone()),
+            DispatchError::Other(err)
+        );
+    }
+}
@@ -0,0 +1,451 @@
+/*!
+Signed extensions add extra data to an extrinsic and to the payload signed
+by its sender. Which extensions a chain expects and in what order is declared
+in its metadata, sube walks that list and encodes the values of every
+extension it knows about.
+
+Chains with custom extensions can register their own handlers which take
+precedence over the built-in ones.
+*/
+use alloc::collections::BTreeMap;
+use codec::{Compact, Encode};
+use scale_info::{PortableRegistry, TypeDef};
+
+use crate::meta_ext::Meta;
+use crate::prelude::*;
+use crate::{Era, Error, JsonValue, Result};
+
+type TypeId = u32;
+
+/// Values known at the moment of building an extrinsic that extensions
+/// can use to encode their data
+pub struct ExtensionContext<'a> {
+    pub registry: &'a PortableRegistry,
+    pub nonce: u64,
+    pub tip: u128,
+    /// JSON representation of the asset used to pay fees with
+    pub fee_asset: Option<&'a JsonValue>,
+    pub era: Era,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub genesis_hash: &'a [u8],
+    /// Hash of the block the era of the extrinsic starts at
+    pub checkpoint: &'a [u8],
+}
+
+/// Encodes the data a signed extension contributes to an extrinsic
+pub trait SignedExtension {
+    /// Data included in the extrinsic, `ty` is the type declared for it in the metadata
+    fn extra(&self, ctx: &ExtensionContext, ty: TypeId) -> Result<Vec<u8>>;
+
+    /// Data that is not part of the extrinsic but is signed along with it
+    fn additional_signed(&self, ctx: &ExtensionContext, ty: TypeId) -> Result<Vec<u8>>;
+}
+
+/// Custom extension handlers by identifier
+#[derive(Default)]
+pub struct Extensions<'a>(BTreeMap<String, Box<dyn SignedExtension + 'a>>);
+
+impl<'a> Extensions<'a> {
+    pub fn register(&mut self, identifier: impl Into<String>, ext: impl SignedExtension + 'a) {
+        self.0.insert(identifier.into(), Box::new(ext));
+    }
+
+    /// Encode the extra and additional signed data of all the extensions declared
+    /// in the metadata in the order they are declared
+    pub fn encode(&self, meta: &impl Meta, ctx: &ExtensionContext) -> Result<(Vec<u8>, Vec<u8>)> {
+        let mut extra = vec![];
+        let mut additional = vec![];
+
+        for ext in meta.signed_extensions() {
+            log::trace!("encoding signed extension {}", ext.identifier);
+            let (ext_extra, ext_additional) = if let Some(h) = self.0.get(ext.identifier) {
+                encode_with(h.as_ref(), ctx, ext.ty, ext.additional_signed)?
+            } else if let Some(h) = builtin(ext.identifier) {
+                encode_with(h, ctx, ext.ty, ext.additional_signed)?
+            } else if is_empty(ctx.registry, ext.ty)
+                && is_empty(ctx.registry, ext.additional_signed)
+            {
+                // extensions without data like `CheckWeight` need no handler
+                continue;
+            } else {
+                return Err(Error::UnknownExtension(ext.identifier.into()));
+            };
+            extra.extend(ext_extra);
+            additional.extend(ext_additional);
+        }
+
+        Ok((extra, additional))
+    }
+}
+
+fn encode_with(
+    ext: &dyn SignedExtension,
+    ctx: &ExtensionContext,
+    ty: TypeId,
+    additional_ty: TypeId,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    Ok((
+        ext.extra(ctx, ty)?,
+        ext.additional_signed(ctx, additional_ty)?,
+    ))
+}
+
+fn builtin(identifier: &str) -> Option<&'static dyn SignedExtension> {
+    Some(match identifier {
+        "CheckSpecVersion" => &CheckSpecVersion,
+        "CheckTxVersion" => &CheckTxVersion,
+        "CheckGenesis" => &CheckGenesis,
+        "CheckMortality" => &CheckMortality,
+        "CheckNonce" => &CheckNonce,
+        "CheckMetadataHash" => &CheckMetadataHash,
+        "PassAuthenticate" => &PassAuthenticate(None),
+        // Kreivo wraps the asset payment to charge fees to pass accounts
+        "ChargeTransactionPayment"
+        | "ChargeAssetTxPayment"
+        | "ChargeTransactionToPassAccount"
+        | "SkipCheckIfFeeless" => &ChargeFee,
+        _ => return None,
+    })
+}
+
+struct CheckSpecVersion;
+impl SignedExtension for CheckSpecVersion {
+    fn extra(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+    fn additional_signed(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(ctx.spec_version.encode())
+    }
+}
+
+struct CheckTxVersion;
+impl SignedExtension for CheckTxVersion {
+    fn extra(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+    fn additional_signed(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(ctx.transaction_version.encode())
+    }
+}
+
+struct CheckGenesis;
+impl SignedExtension for CheckGenesis {
+    fn extra(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+    fn additional_signed(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(ctx.genesis_hash.to_vec())
+    }
+}
+
+struct CheckMortality;
+impl SignedExtension for CheckMortality {
+    fn extra(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(ctx.era.encode())
+    }
+    fn additional_signed(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(ctx.checkpoint.to_vec())
+    }
+}
+
+struct CheckNonce;
+impl SignedExtension for CheckNonce {
+    fn extra(&self, ctx: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(Compact(ctx.nonce).encode())
+    }
+    fn additional_signed(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+}
+
+// Metadata hash verification is not supported, it's signaled as disabled
+struct CheckMetadataHash;
+impl SignedExtension for CheckMetadataHash {
+    fn extra(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        // Mode::Disabled
+        Ok(vec![0x00])
+    }
+    fn additional_signed(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        // no metadata hash
+        Ok(vec![0x00])
+    }
+}
+
+/// Kreivo's passes authenticate extrinsics with this extension, given the
+/// `AuthenticateParams` of a pass in JSON, the device and the credential it
+/// signed with, the extrinsic is dispatched as the pass account. Without them
+/// the extrinsic is only signed by its sender, like the built-in handler does
+pub struct PassAuthenticate(pub Option<JsonValue>);
+impl SignedExtension for PassAuthenticate {
+    fn extra(&self, ctx: &ExtensionContext, ty: TypeId) -> Result<Vec<u8>> {
+        let Some(params) = &self.0 else {
+            return Ok(vec![0x00]);
+        };
+        let params_ty = optional_type(ctx.registry, ty)?;
+        let params = scales::to_vec_with_info(params, (ctx.registry, params_ty).into())
+            .map_err(|e| Error::Encode(e.to_string()))?;
+        Ok([vec![0x01], params].concat())
+    }
+    fn additional_signed(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+}
+
+// Type of the value of an `Option`, extensions wrapping it are looked through
+fn optional_type(registry: &PortableRegistry, ty: TypeId) -> Result<TypeId> {
+    let ty_info = registry.resolve(ty).ok_or(Error::BadMetadata)?;
+    match &ty_info.type_def {
+        TypeDef::Composite(c) if c.fields.len() == 1 => optional_type(registry, c.fields[0].ty.id),
+        TypeDef::Variant(v) if ty_info.path.segments.last().is_some_and(|s| s == "Option") => v
+            .variants
+            .iter()
+            .find(|v| v.name == "Some")
+            .and_then(|v| v.fields.first())
+            .map(|f| f.ty.id)
+            .ok_or(Error::BadMetadata),
+        _ => Err(Error::Encode(format!("expected an optional type {}", ty))),
+    }
+}
+
+/// Fee payment extensions come in different shapes, the plain `ChargeTransactionPayment`
+/// only has a tip while the asset based ones have an optional asset id and others
+/// wrap an inner payment extension, the type information tells them apart.
+struct ChargeFee;
+impl SignedExtension for ChargeFee {
+    fn extra(&self, ctx: &ExtensionContext, ty: TypeId) -> Result<Vec<u8>> {
+        let mut asset_used = false;
+        let extra = encode_fee(ctx, ty, &mut asset_used)?;
+        if ctx.fee_asset.is_some() && !asset_used {
+            return Err(Error::Encode(
+                "Chain doesn't support paying fees with assets".into(),
+            ));
+        }
+        Ok(extra)
+    }
+    fn additional_signed(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+        Ok(vec![])
+    }
+}
+
+fn encode_fee(ctx: &ExtensionContext, ty: TypeId, asset_used: &mut bool) -> Result<Vec<u8>> {
+    let ty_info = ctx.registry.resolve(ty).ok_or(Error::BadMetadata)?;
+    match &ty_info.type_def {
+        TypeDef::Compact(_) => Ok(Compact(ctx.tip).encode()),
+        TypeDef::Composite(c) => Ok(c
+            .fields
+            .iter()
+            .map(|f| encode_fee(ctx, f.ty.id, asset_used))
+            .collect::<Result<Vec<_>>>()?
+            .concat()),
+        // the asset to pay fees with, paying with the native token by default
+        TypeDef::Variant(_) if ty_info.path.segments.last().is_some_and(|s| s == "Option") => {
+            let Some(asset) = ctx.fee_asset else {
+                return Ok(vec![0x00]);
+            };
+            let asset_ty = optional_type(ctx.registry, ty)?;
+            let asset = scales::to_vec_with_info(asset, (ctx.registry, asset_ty).into())
+                .map_err(|e| Error::Encode(e.to_string()))?;
+            *asset_used = true;
+            Ok([vec![0x01], asset].concat())
+        }
+        _ if is_empty(ctx.registry, ty) => Ok(vec![]),
+        _ => Err(Error::Encode(format!("unexpected fee payment type {}", ty))),
+    }
+}
+
+// Types like `()` or `PhantomData` don't take any space once encoded
+fn is_empty(registry: &PortableRegistry, ty: TypeId) -> bool {
+    match registry.resolve(ty).map(|t| &t.type_def) {
+        Some(TypeDef::Composite(c)) => c.fields.iter().all(|f| is_empty(registry, f.ty.id)),
+        Some(TypeDef::Tuple(t)) => t.fields.iter().all(|f| is_empty(registry, f.id)),
+        Some(TypeDef::Array(a)) => a.len == 0 || is_empty(registry, a.type_param.id),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::fixtures::{kreivo, kusama};
+    use crate::Metadata;
+    use serde_json::json;
+
+    fn context(registry: &PortableRegistry) -> ExtensionContext<'_> {
+        ExtensionContext {
+            registry,
+            nonce: 300,
+            tip: 0,
+            fee_asset: None,
+            era: Era::mortal(64, 100),
+            spec_version: 1_002_000,
+            transaction_version: 26,
+            genesis_hash: &[1; 32],
+            checkpoint: &[2; 32],
+        }
+    }
+
+    // extra and additional signed data of a built-in extension with the types
+    // the metadata declares for it
+    fn encode_builtin(
+        meta: &Metadata,
+        ctx: &ExtensionContext,
+        identifier: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        let ext = meta
+            .signed_extensions()
+            .find(|e| e.identifier == identifier)
+            .expect("extension in metadata");
+        let handler = builtin(identifier).expect("built-in extension");
+        encode_with(handler, ctx, ext.ty, ext.additional_signed)
+    }
+
+    #[test]
+    fn check_versions_and_genesis() {
+        let meta = kusama();
+        let ctx = context(meta.registry());
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckSpecVersion").unwrap(),
+            (vec![], 1_002_000u32.encode())
+        );
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckTxVersion").unwrap(),
+            (vec![], 26u32.encode())
+        );
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckGenesis").unwrap(),
+            (vec![], vec![1; 32])
+        );
+    }
+
+    #[test]
+    fn check_mortality() {
+        let meta = kusama();
+        let ctx = context(meta.registry());
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckMortality").unwrap(),
+            (Era::mortal(64, 100).encode(), vec![2; 32])
+        );
+        let ctx = ExtensionContext {
+            era: Era::Immortal,
+            checkpoint: &[1; 32],
+            ..context(meta.registry())
+        };
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckMortality").unwrap(),
+            (vec![0x00], vec![1; 32])
+        );
+    }
+
+    #[test]
+    fn check_nonce() {
+        let meta = kusama();
+        let ctx = context(meta.registry());
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckNonce").unwrap(),
+            (Compact(300u64).encode(), vec![])
+        );
+    }
+
+    #[test]
+    fn check_metadata_hash_disabled() {
+        let meta = kusama();
+        let ctx = context(meta.registry());
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "CheckMetadataHash").unwrap(),
+            (vec![0x00], vec![0x00])
+        );
+    }
+
+    #[test]
+    fn charge_transaction_payment() {
+        let meta = kusama();
+        let ctx = ExtensionContext {
+            tip: 1_000,
+            ..context(meta.registry())
+        };
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "ChargeTransactionPayment").unwrap(),
+            (Compact(1_000u128).encode(), vec![])
+        );
+
+        let asset = json!({ "parents": 1, "interior": "Here" });
+        let ctx = ExtensionContext {
+            fee_asset: Some(&asset),
+            ..context(meta.registry())
+        };
+        assert!(matches!(
+            encode_builtin(&meta, &ctx, "ChargeTransactionPayment"),
+            Err(Error::Encode(_))
+        ));
+    }
+
+    #[test]
+    fn charge_asset_tx_payment() {
+        let meta = kreivo();
+        let ctx = ExtensionContext {
+            tip: 1_000,
+            ..context(meta.registry())
+        };
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "ChargeAssetTxPayment").unwrap(),
+            ([Compact(1_000u128).encode(), vec![0x00]].concat(), vec![])
+        );
+    }
+
+    #[test]
+    fn pass_authenticate_none() {
+        let meta = kreivo();
+        let ctx = context(meta.registry());
+        assert_eq!(
+            encode_builtin(&meta, &ctx, "PassAuthenticate").unwrap(),
+            (vec![0x00], vec![])
+        );
+    }
+
+    #[test]
+    fn all_builtin_extensions() {
+        let meta = kreivo();
+        let ctx = context(meta.registry());
+        let (extra, additional) = Extensions::default().encode(&meta, &ctx).unwrap();
+        assert_eq!(
+            extra,
+            [
+                &[0x00][..],
+                &Era::mortal(64, 100).encode(),
+                &Compact(300u64).encode(),
+                &[0x00, 0x00],
+            ]
+            .concat()
+        );
+        assert_eq!(
+            additional,
+            [
+                &1_002_000u32.encode()[..],
+                &26u32.encode(),
+                &[1; 32],
+                &[2; 32]
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn custom_extensions_take_precedence() {
+        struct Pass;
+        impl SignedExtension for Pass {
+            fn extra(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+                Ok(vec![0x01, 0x42])
+            }
+            fn additional_signed(&self, _: &ExtensionContext, _: TypeId) -> Result<Vec<u8>> {
+                Ok(vec![])
+            }
+        }
+        let meta = kreivo();
+        let ctx = context(meta.registry());
+        let mut extensions = Extensions::default();
+        extensions.register("PassAuthenticate", Pass);
+        let (extra, _) = extensions.encode(&meta, &ctx).unwrap();
+        assert_eq!(extra[..2], [0x01, 0x42]);
+    }
+}
@@ -0,0 +1,355 @@
+/*!
+The address and signature of an extrinsic are encoded with the types the
+runtime declares for them in its metadata. Most chains use `MultiAddress` and
+`MultiSignature`, others like Ethereum compatible chains use 20 byte accounts
+and a bare ECDSA signature.
+
+The header of an extrinsic is its format version with the kind of extrinsic
+in the two most significant bits. Signed extrinsics only exist in version 4,
+version 5 introduced general transactions that are authorized by the
+transaction extensions of the runtime instead of a signature. V14 and V15
+metadata declare a single extrinsic version, it's 4 for current runtimes even
+when they also accept version 5, so general transactions are only built for
+runtimes whose metadata declares version 5 and fail with
+[`Error::UnsupportedExtrinsic`] otherwise.
+
+Extrinsics are decoded back into their parts with the same types, the call is
+decoded through the `RuntimeCall` type into its pallet, call and arguments.
+*/
+use alloc::collections::BTreeMap;
+use codec::{Compact, Decode};
+use scale_info::{PortableRegistry, TypeDef, TypeDefPrimitive};
+use serde::Serialize;
+
+use crate::meta_ext::Meta;
+use crate::prelude::*;
+use crate::{Error, JsonValue, Result, Scheme, Value};
+
+type TypeId = u32;
+
+const SIGNED: u8 = 0b1000_0000;
+const GENERAL: u8 = 0b0100_0000;
+
+/// How an extrinsic is authorized, it decides the header it's built with
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtrinsicMode {
+    /// Signed by the account that sends it
+    #[default]
+    Signed,
+    /// Bare extrinsic without origin, the runtime validates the call itself
+    Unsigned,
+    /// Version 5 transaction authorized by the transaction extensions,
+    /// it requires metadata that declares the version 5 extrinsic format
+    General,
+}
+
+/// Header of an extrinsic of the given mode in the latest format version
+/// the runtime supports for it, as far as its metadata tells
+pub(crate) fn header(meta: &impl Meta, mode: ExtrinsicMode) -> Result<u8> {
+    let versions = meta.extrinsic_versions();
+    let supported = |v| versions.contains(&v).then_some(v);
+
+    match mode {
+        ExtrinsicMode::Signed => supported(4).map(|v| SIGNED | v),
+        ExtrinsicMode::Unsigned => supported(5).or_else(|| supported(4)),
+        ExtrinsicMode::General => supported(5).map(|v| GENERAL | v),
+    }
+    .ok_or(Error::UnsupportedExtrinsic(mode))
+}
+
+/// Encode the account as the address type of the runtime
+pub(crate) fn encode_address(meta: &impl Meta, account: &[u8]) -> Result<Vec<u8>> {
+    let Some(types) = meta.extrinsic_types() else {
+        // assume a `MultiAddress::Id` when the metadata doesn't tell
+        return Ok([&[0x00], account].concat());
+    };
+    encode_account(meta.registry(), types.address, account)
+}
+
+/// Encode the account as the given type, the first variant of a `MultiAddress`
+/// like enum that fits the account is used
+pub(crate) fn encode_account(
+    registry: &PortableRegistry,
+    ty: TypeId,
+    account: &[u8],
+) -> Result<Vec<u8>> {
+    match &registry.resolve(ty).ok_or(Error::BadMetadata)?.type_def {
+        TypeDef::Variant(address) => {
+            let preferred = if account.len() == 20 {
+                ["Id", "Address20"]
+            } else {
+                ["Id", "Address32"]
+            };
+            preferred
+                .iter()
+                .filter_map(|name| address.variants.iter().find(|v| v.name == *name))
+                .find(|v| {
+                    v.fields.len() == 1
+                        && fixed_len(registry, v.fields[0].ty.id) == Some(account.len())
+                })
+                .map(|v| [&[v.index], account].concat())
+        }
+        _ => (fixed_len(registry, ty) == Some(account.len())).then(|| account.to_vec()),
+    }
+    .ok_or_else(|| Error::Encode(format!("{} bytes account not supported", account.len())))
+}
+
+/// Index of the variant of a `MultiSignature` like enum for the scheme,
+/// `None` when the runtime expects a signature without a variant prefix
+pub(crate) fn signature_variant(meta: &impl Meta, scheme: Scheme) -> Result<Option<u8>> {
+    let Some(types) = meta.extrinsic_types() else {
+        return Ok(Some(match scheme {
+            Scheme::Ed25519 => 0,
+            Scheme::Sr25519 => 1,
+            Scheme::Ecdsa => 2,
+        }));
+    };
+    let registry = meta.registry();
+    let len = Some(scheme.signature_len());
+
+    let ty = registry
+        .resolve(types.signature)
+        .ok_or(Error::BadMetadata)?;
+
+    match &ty.type_def {
+        TypeDef::Variant(signature) => signature
+            .variants
+            .iter()
+            .find(|v| v.name == scheme.name())
+            .filter(|v| v.fields.len() == 1 && fixed_len(registry, v.fields[0].ty.id) == len)
+            .map(|v| Some(v.index)),
+        _ => (fixed_len(registry, types.signature) == len).then_some(None),
+    }
+    .ok_or_else(|| Error::Encode(format!("{:?} signatures not supported", scheme)))
+}
+
+/// An extrinsic split into its parts with their values decoded
+#[derive(Serialize, Debug, Clone)]
+pub struct DecodedExtrinsic {
+    pub version: u8,
+    pub mode: ExtrinsicMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<JsonValue>,
+    /// Values of the signed extensions by identifier, extensions without data are left out
+    pub extensions: BTreeMap<String, JsonValue>,
+    pub call: DecodedCall,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DecodedCall {
+    pub pallet: String,
+    pub call: String,
+    /// Arguments by name, or in order when they have no names
+    pub args: JsonValue,
+}
+
+/// Decode a length prefixed extrinsic like the ones `submit` sends
+pub fn decode(meta: &impl Meta, extrinsic: &[u8]) -> Result<DecodedExtrinsic> {
+    let types = meta.extrinsic_types().ok_or(Error::BadMetadata)?;
+    let input = &mut &extrinsic[..];
+    let len = Compact::<u32>::decode(input).map_err(Error::Decode)?.0;
+    if input.len() != len as usize {
+        return Err(malformed());
+    }
+    let mut cursor = Cursor {
+        registry: meta.registry(),
+        data: input,
+    };
+
+    let header = cursor.take_bytes(1)?[0];
+    let version = header & !(SIGNED | GENERAL);
+    let mode = match header & (SIGNED | GENERAL) {
+        SIGNED => ExtrinsicMode::Signed,
+        GENERAL => ExtrinsicMode::General,
+        0 => ExtrinsicMode::Unsigned,
+        _ => return Err(malformed()),
+    };
+
+    let (mut address, mut signature, mut extensions) = (None, None, BTreeMap::new());
+    if mode != ExtrinsicMode::Unsigned {
+        if mode == ExtrinsicMode::Signed {
+            address = Some(cursor.value(types.address)?);
+            signature = Some(cursor.value(types.signature)?);
+        } else {
+            // version of the transaction extensions
+            cursor.take_bytes(1)?;
+        }
+        for ext in meta.signed_extensions() {
+            let data = cursor.take(ext.ty)?;
+            if !data.is_empty() {
+                let value = Value::new(data.to_vec(), ext.ty, cursor.registry);
+                extensions.insert(ext.identifier.into(), value.into());
+            }
+        }
+    }
+
+    Ok(DecodedExtrinsic {
+        version,
+        mode,
+        address,
+        signature,
+        extensions,
+        call: decode_call(meta, cursor.data)?,
+    })
+}
+
+/// Decode an encoded `RuntimeCall` into its pallet, call and arguments
+pub fn decode_call(meta: &impl Meta, call: &[u8]) -> Result<DecodedCall> {
+    let types = meta.extrinsic_types().ok_or(Error::BadMetadata)?;
+    let registry = meta.registry();
+    let variant = |ty: TypeId, index: u8| match &registry.resolve(ty)?.type_def {
+        TypeDef::Variant(v) => v.variants.iter().find(|v| v.index == index),
+        _ => None,
+    };
+
+    let (&pallet_index, call) = call.split_first().ok_or_else(malformed)?;
+    let pallet = variant(types.call, pallet_index).ok_or_else(malformed)?;
+    let calls_ty = pallet.fields.first().ok_or_else(malformed)?.ty.id;
+    let (&call_index, args) = call.split_first().ok_or_else(malformed)?;
+    let call = variant(calls_ty, call_index).ok_or_else(malformed)?;
+
+    let mut cursor = Cursor {
+        registry,
+        data: args,
+    };
+    let named = call.fields.iter().all(|f| f.name.is_some());
+    let mut values = Vec::with_capacity(call.fields.len());
+    for field in call.fields.iter() {
+        values.push((field.name.clone(), cursor.value(field.ty.id)?));
+    }
+    if !cursor.data.is_empty() {
+        return Err(malformed());
+    }
+
+    let args = if named {
+        JsonValue::Object(
+            values
+                .into_iter()
+                .map(|(name, value)| (name.unwrap_or_default(), value))
+                .collect(),
+        )
+    } else {
+        JsonValue::Array(values.into_iter().map(|(_, value)| value).collect())
+    };
+
+    Ok(DecodedCall {
+        pallet: pallet.name.clone(),
+        call: call.name.clone(),
+        args,
+    })
+}
+
+fn malformed() -> Error {
+    Error::Decode("malformed extrinsic".into())
+}
+
+/// Reads values of the registry's types from the start of the data
+struct Cursor<'a> {
+    registry: &'a PortableRegistry,
+    data: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take_bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        if size > self.data.len() {
+            return Err(malformed());
+        }
+        let (value, rest) = self.data.split_at(size);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn take(&mut self, ty: TypeId) -> Result<&'a [u8]> {
+        let size = encoded_size(self.registry, ty, self.data).ok_or_else(malformed)?;
+        self.take_bytes(size)
+    }
+
+    fn value(&mut self, ty: TypeId) -> Result<JsonValue> {
+        let data = self.take(ty)?;
+        Ok(Value::new(data.to_vec(), ty, self.registry).into())
+    }
+}
+
+/// Size of the value of the given type at the start of the data, `None` when
+/// the data is too short or isn't a valid encoding of the type
+pub(crate) fn encoded_size(registry: &PortableRegistry, ty: TypeId, data: &[u8]) -> Option<usize> {
+    let size = match &registry.resolve(ty)?.type_def {
+        TypeDef::Primitive(p) => match p {
+            TypeDefPrimitive::Bool | TypeDefPrimitive::U8 | TypeDefPrimitive::I8 => 1,
+            TypeDefPrimitive::U16 | TypeDefPrimitive::I16 => 2,
+            TypeDefPrimitive::U32 | TypeDefPrimitive::I32 | TypeDefPrimitive::Char => 4,
+            TypeDefPrimitive::U64 | TypeDefPrimitive::I64 => 8,
+            TypeDefPrimitive::U128 | TypeDefPrimitive::I128 => 16,
+            TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => 32,
+            TypeDefPrimitive::Str => {
+                let len = Compact::<u32>::decode(&mut &data[..]).ok()?.0 as usize;
+                compact_size(data)? + len
+            }
+        },
+        TypeDef::Composite(c) => fields_size(registry, data, c.fields.iter().map(|f| f.ty.id), 0)?,
+        TypeDef::Variant(v) => {
+            let index = *data.first()?;
+            let variant = v.variants.iter().find(|v| v.index == index)?;
+            fields_size(registry, data, variant.fields.iter().map(|f| f.ty.id), 1)?
+        }
+        TypeDef::Sequence(s) => {
+            let len = Compact::<u32>::decode(&mut &data[..]).ok()?.0 as usize;
+            // every item takes at least a byte, longer sequences can't be valid
+            if len > data.len() {
+                return None;
+            }
+            let item = s.type_param.id;
+            let items = core::iter::repeat_n(item, len);
+            fields_size(registry, data, items, compact_size(data)?)?
+        }
+        TypeDef::Array(a) => {
+            let item = a.type_param.id;
+            let items = core::iter::repeat_n(item, a.len as usize);
+            fields_size(registry, data, items, 0)?
+        }
+        TypeDef::Tuple(t) => fields_size(registry, data, t.fields.iter().map(|f| f.id), 0)?,
+        TypeDef::Compact(_) => compact_size(data)?,
+        TypeDef::BitSequence(_) => return None,
+    };
+
+    (size <= data.len()).then_some(size)
+}
+
+/// Size of consecutive values of the given types that start at the offset
+fn fields_size(
+    registry: &PortableRegistry,
+    data: &[u8],
+    mut tys: impl Iterator<Item = TypeId>,
+    offset: usize,
+) -> Option<usize> {
+    tys.try_fold(offset, |size, ty| {
+        Some(size + encoded_size(registry, ty, data.get(size..)?)?)
+    })
+}
+
+fn compact_size(data: &[u8]) -> Option<usize> {
+    let first = *data.first()?;
+    Some(match first & 0b11 {
+        0b00 => 1,
+        0b01 => 2,
+        0b10 => 4,
+        _ => (first >> 2) as usize + 5,
+    })
+}
+
+/// Size of types made of bytes like `AccountId32` or `[u8; 65]`
+fn fixed_len(registry: &PortableRegistry, ty: TypeId) -> Option<usize> {
+    match &registry.resolve(ty)?.type_def {
+        TypeDef::Array(a) => {
+            let item = fixed_len(registry, a.type_param.id)?;
+            Some(item * a.len as usize)
+        }
+        TypeDef::Composite(c) if c.fields.len() == 1 => fixed_len(registry, c.fields[0].ty.id),
+        TypeDef::Tuple(t) if t.fields.len() == 1 => fixed_len(registry, t.fields[0].id),
+        TypeDef::Primitive(TypeDefPrimitive::U8) => Some(1),
+        _ => None,
+    }
+}
@@ -0,0 +1,414 @@
+/*!
+Public nodes go down or get overloaded every now and then, a failover backend
+spreads the requests over several nodes of the same chain and moves on to the
+next one when a node can't be reached.
+
+Nodes have to share the genesis hash of the first ones to answer. Nodes that
+can't be reached are left out until some time has passed, they are checked
+again before being used, or until a health check finds them reachable again.
+*/
+use core::future::Future;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+
+use crate::prelude::*;
+#[cfg(feature = "ws")]
+use crate::rpc::Subscription;
+use crate::rpc::{self, error, is_connection_error, Rpc, RpcResult};
+use crate::{Error, Result};
+
+/// How the endpoint a request is sent to is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Requests go to the available endpoints in turns
+    #[default]
+    RoundRobin,
+    /// Requests go to the endpoint that answered its last health check the fastest
+    Latency,
+}
+
+/// Settings of the failover backend
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub strategy: Strategy,
+    /// Time an unreachable endpoint is left out before trying it again
+    pub retry_after: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            strategy: Strategy::default(),
+            retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Health {
+    Up {
+        latency: Duration,
+    },
+    Down {
+        since: Instant,
+    },
+    /// The endpoint belongs to another chain and is never used
+    WrongChain,
+}
+
+struct Endpoint<R> {
+    rpc: R,
+    health: Mutex<Health>,
+}
+
+impl<R> Endpoint<R> {
+    fn health(&self) -> Health {
+        *self.health.lock().expect("not poisoned")
+    }
+
+    fn set_health(&self, health: Health) {
+        *self.health.lock().expect("not poisoned") = health;
+    }
+}
+
+/// JSONRpc backend that sends requests to one of several endpoints of the same chain
+pub struct Failover<R> {
+    endpoints: Vec<Endpoint<R>>,
+    genesis: String,
+    options: Options,
+    next: AtomicUsize,
+}
+
+impl<R: Rpc> Failover<R> {
+    /// Check that the endpoints belong to the same chain, at least one of them
+    /// has to be reachable
+    pub async fn new(endpoints: Vec<R>, options: Options) -> Result<Self> {
+        let checks = join_all(endpoints.iter().map(genesis_hash)).await;
+
+        let mut genesis = None;
+        for (i, check) in checks.iter().enumerate() {
+            match (check, &genesis) {
+                (Ok((hash, _)), None) => genesis = Some(hash.clone()),
+                (Ok((hash, _)), Some(genesis)) if hash != genesis => {
+                    return Err(Error::Platform(format!(
+                        "Endpoint {} belongs to a chain with genesis {} instead of {}",
+                        i, hash, genesis
+                    )))
+                }
+                (Err(err), _) => log::warn!("Endpoint {} is unreachable: {}", i, err),
+                _ => {}
+            }
+        }
+        let genesis = genesis.ok_or(Error::ChainUnavailable)?;
+
+        let now = Instant::now();
+        let endpoints = endpoints
+            .into_iter()
+            .zip(checks)
+            .map(|(rpc, check)| Endpoint {
+                rpc,
+                health: Mutex::new(match check {
+                    Ok((_, latency)) => Health::Up { latency },
+                    Err(_) => Health::Down { since: now },
+                }),
+            })
+            .collect();
+
+        Ok(Failover {
+            endpoints,
+            genesis,
+            options,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Check all the endpoints at once measuring their latency and
+    /// bringing back the ones that are reachable again
+    pub async fn check_health(&self) {
+        join_all(self.endpoints.iter().map(|e| self.check(e))).await;
+    }
+
+    /// Number of endpoints that answered their last request
+    pub fn available(&self) -> usize {
+        self.endpoints
+            .iter()
+            .filter(|e| matches!(e.health(), Health::Up { .. }))
+            .count()
+    }
+
+    async fn check(&self, endpoint: &Endpoint<R>) -> bool {
+        let health = match genesis_hash(&endpoint.rpc).await {
+            Ok((hash, latency)) if hash == self.genesis => Health::Up { latency },
+            Ok((hash, _)) => {
+                log::error!("Endpoint belongs to a chain with genesis {}", hash);
+                Health::WrongChain
+            }
+            Err(err) => {
+                log::debug!("Endpoint is still unreachable: {}", err);
+                Health::Down {
+                    since: Instant::now(),
+                }
+            }
+        };
+        endpoint.set_health(health);
+        matches!(health, Health::Up { .. })
+    }
+
+    // Endpoints in the order they should be tried, the ones that went down
+    // recently are only tried when the others failed
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut up = vec![];
+        let mut retry = vec![];
+        let mut down = vec![];
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            match endpoint.health() {
+                Health::Up { latency } => up.push((i, latency)),
+                Health::Down { since } if now - since >= self.options.retry_after => retry.push(i),
+                Health::Down { .. } => down.push(i),
+                Health::WrongChain => {}
+            }
+        }
+
+        match self.options.strategy {
+            Strategy::RoundRobin if !up.is_empty() => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % up.len();
+                up.rotate_left(start);
+            }
+            Strategy::RoundRobin => {}
+            Strategy::Latency => up.sort_by_key(|(_, latency)| *latency),
+        }
+
+        up.into_iter()
+            .map(|(i, _)| i)
+            .chain(retry)
+            .chain(down)
+            .collect()
+    }
+
+    // Send the request to the endpoints until one of them can be reached,
+    // errors for which `skip` is true move on to the next endpoint as well
+    async fn failover<'a, T, F, Fut>(
+        &'a self,
+        request: F,
+        skip: fn(&error::Error) -> bool,
+    ) -> RpcResult<T>
+    where
+        F: Fn(&'a R) -> Fut,
+        Fut: Future<Output = RpcResult<T>>,
+    {
+        let mut last_err = None;
+        for i in self.candidates() {
+            let endpoint = &self.endpoints[i];
+            let up = matches!(endpoint.health(), Health::Up { .. });
+            if !up && !self.check(endpoint).await {
+                continue;
+            }
+
+            match request(&endpoint.rpc).await {
+                Err(err) if is_connection_error(&err) => {
+                    log::warn!("Endpoint {} is unreachable: {}", i, err);
+                    endpoint.set_health(Health::Down {
+                        since: Instant::now(),
+                    });
+                    last_err = Some(err);
+                }
+                Err(err) if skip(&err) => last_err = Some(err),
+                res => return res,
+            }
+        }
+        Err(last_err.unwrap_or_else(|| rpc::connection_error("No endpoint available").into()))
+    }
+}
+
+impl<R: Rpc> Rpc for Failover<R> {
+    async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.failover(|rpc| rpc.rpc(method, params), |_| false)
+            .await
+    }
+
+    async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.failover(|rpc| rpc.rpc_batch(requests), |_| false)
+            .await
+    }
+
+    /// The subscription stays with the endpoint it was created on,
+    /// endpoints that don't support subscriptions are skipped
+    #[cfg(feature = "ws")]
+    async fn subscribe(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> RpcResult<Subscription> {
+        self.failover(
+            |rpc| rpc.subscribe(method, params, unsubscribe),
+            |err| matches!(err, error::Error::Rpc(e) if e.code == error::StandardError::MethodNotFound as i32),
+        )
+        .await
+    }
+}
+
+// Genesis hash of the chain of an endpoint and the time it took to answer
+async fn genesis_hash(rpc: &impl Rpc) -> RpcResult<(String, Duration)> {
+    let start = Instant::now();
+    let hash = rpc.rpc::<String>("chain_getBlockHash", &["0"]).await?;
+    Ok((hash, start.elapsed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::AtomicBool;
+
+    const GENESIS: &str = "0x01";
+
+    struct Node {
+        genesis: &'static str,
+        up: AtomicBool,
+        delay: Duration,
+        requests: AtomicUsize,
+    }
+
+    impl Node {
+        fn new(genesis: &'static str) -> Self {
+            Node {
+                genesis,
+                up: AtomicBool::new(true),
+                delay: Duration::ZERO,
+                requests: AtomicUsize::new(0),
+            }
+        }
+
+        fn slow(delay: Duration) -> Self {
+            Node {
+                delay,
+                ..Node::new(GENESIS)
+            }
+        }
+
+        fn down(self) -> Self {
+            self.up.store(false, Ordering::Relaxed);
+            self
+        }
+    }
+
+    impl Rpc for Node {
+        async fn rpc<T>(&self, method: &str, _params: &[&str]) -> RpcResult<T>
+        where
+            T: for<'de> serde::Deserialize<'de>,
+        {
+            async_std::task::sleep(self.delay).await;
+            if !self.up.load(Ordering::Relaxed) {
+                return Err(rpc::connection_error("Connection refused").into());
+            }
+            let res = match method {
+                "chain_getBlockHash" => serde_json::json!(self.genesis),
+                _ => serde_json::json!(self.requests.fetch_add(1, Ordering::Relaxed)),
+            };
+            Ok(serde_json::from_value(res)?)
+        }
+    }
+
+    fn requests(chain: &Failover<Node>) -> Vec<usize> {
+        chain
+            .endpoints
+            .iter()
+            .map(|e| e.rpc.requests.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    async fn send(chain: &Failover<Node>, n: usize) {
+        for _ in 0..n {
+            chain.rpc::<u32>("system_number", &[]).await.unwrap();
+        }
+    }
+
+    #[async_std::test]
+    async fn round_robin() {
+        let nodes = vec![Node::new(GENESIS), Node::new(GENESIS), Node::new(GENESIS)];
+        let chain = Failover::new(nodes, Options::default()).await.unwrap();
+
+        send(&chain, 6).await;
+        assert_eq!(requests(&chain), vec![2, 2, 2]);
+    }
+
+    #[async_std::test]
+    async fn fastest_endpoint_first() {
+        let nodes = vec![
+            Node::slow(Duration::from_millis(30)),
+            Node::slow(Duration::from_millis(1)),
+            Node::slow(Duration::from_millis(15)),
+        ];
+        let options = Options {
+            strategy: Strategy::Latency,
+            ..Options::default()
+        };
+        let chain = Failover::new(nodes, options).await.unwrap();
+
+        send(&chain, 3).await;
+        assert_eq!(requests(&chain), vec![0, 3, 0]);
+    }
+
+    #[async_std::test]
+    async fn failover_to_reachable_endpoints() {
+        let nodes = vec![Node::new(GENESIS), Node::new(GENESIS).down()];
+        let chain = Failover::new(nodes, Options::default()).await.unwrap();
+        assert_eq!(chain.available(), 1);
+
+        chain.endpoints[1].rpc.up.store(true, Ordering::Relaxed);
+        chain.endpoints[0].rpc.up.store(false, Ordering::Relaxed);
+        // the unreachable endpoint is checked again as the last resort
+        send(&chain, 2).await;
+        assert_eq!(requests(&chain), vec![0, 2]);
+        assert_eq!(chain.available(), 1);
+
+        chain.endpoints[0].rpc.up.store(true, Ordering::Relaxed);
+        chain.check_health().await;
+        assert_eq!(chain.available(), 2);
+    }
+
+    #[async_std::test]
+    async fn fail_when_all_endpoints_are_down() {
+        let nodes = vec![Node::new(GENESIS), Node::new(GENESIS)];
+        let chain = Failover::new(nodes, Options::default()).await.unwrap();
+        for endpoint in &chain.endpoints {
+            endpoint.rpc.up.store(false, Ordering::Relaxed);
+        }
+
+        let err = chain.rpc::<u32>("system_number", &[]).await.unwrap_err();
+        assert!(is_connection_error(&err));
+        assert_eq!(chain.available(), 0);
+    }
+
+    #[async_std::test]
+    async fn endpoints_of_another_chain() {
+        let nodes = vec![Node::new(GENESIS), Node::new("0x02")];
+        assert!(Failover::new(nodes, Options::default()).await.is_err());
+
+        // endpoints that were down are checked before using them
+        let nodes = vec![Node::new(GENESIS), Node::new("0x02").down()];
+        let chain = Failover::new(nodes, Options::default()).await.unwrap();
+        chain.endpoints[1].rpc.up.store(true, Ordering::Relaxed);
+        chain.check_health().await;
+
+        send(&chain, 2).await;
+        assert_eq!(requests(&chain), vec![2, 0]);
+        assert_eq!(chain.available(), 1);
+    }
+
+    #[async_std::test]
+    async fn no_reachable_endpoints() {
+        let nodes = vec![Node::new(GENESIS).down()];
+        assert!(Failover::new(nodes, Options::default()).await.is_err());
+    }
+}
@@ -30,37 +30,38 @@ pub fn hash<I: AsRef<[u8]>>(hasher: &Hasher, input: I) -> Vec<u8> {
         Hasher::Blake2_128 => digest::<Blake2b<U16>>(input).to_vec(),
         Hasher::Blake2_256 => digest::<Blake2b<U32>>(input).to_vec(),
         Hasher::Blake2_128Concat => [digest::<Blake2b<U16>>(input).as_slice(), input].concat(),
-        Hasher::Twox128 => twox_hash(input),
-        Hasher::Twox256 => unimplemented!(),
+        Hasher::Twox128 => twox_hash(input, 2),
+        Hasher::Twox256 => twox_hash(input, 4),
         Hasher::Twox64Concat => twox_hash_concat(input),
         Hasher::Identity => input.into(),
     }
 }
 
-fn twox_hash_concat(input: &[u8]) -> Vec<u8> {
-    let mut dest = [0; 8];
-    let mut h = twox_hash::XxHash64::with_seed(0);
-
-    h.write(input);
-    let r = h.finish();
-    dest.copy_from_slice(&r.to_le_bytes());
-    [dest.as_ref(), input].concat()
+/// Length of the hash a hasher prefixes keys with and whether the
+/// key is concatenated to it so it can be recovered
+pub fn hash_len(hasher: &Hasher) -> (usize, bool) {
+    match hasher {
+        Hasher::Blake2_128 | Hasher::Twox128 => (16, false),
+        Hasher::Blake2_256 | Hasher::Twox256 => (32, false),
+        Hasher::Blake2_128Concat => (16, true),
+        Hasher::Twox64Concat => (8, true),
+        Hasher::Identity => (0, true),
+    }
 }
 
-fn twox_hash(input: &[u8]) -> Vec<u8> {
-    let mut dest: [u8; 16] = [0; 16];
-
-    let mut h0 = twox_hash::XxHash64::with_seed(0);
-    let mut h1 = twox_hash::XxHash64::with_seed(1);
-    h0.write(input);
-    h1.write(input);
-    let r0 = h0.finish();
-    let r1 = h1.finish();
+fn twox_hash_concat(input: &[u8]) -> Vec<u8> {
+    [twox_hash(input, 1), input.into()].concat()
+}
 
-    let (first, last) = dest.split_at_mut(8);
-    first.copy_from_slice(&r0.to_le_bytes());
-    last.copy_from_slice(&r1.to_le_bytes());
-    dest.into()
+/// XxHash64 runs with consecutive seeds starting at 0 concatenated as little endian
+fn twox_hash(input: &[u8], runs: u64) -> Vec<u8> {
+    (0..runs)
+        .flat_map(|seed| {
+            let mut h = twox_hash::XxHash64::with_seed(seed);
+            h.write(input);
+            h.finish().to_le_bytes()
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -68,10 +69,123 @@ mod tests {
     use super::*;
     use hex_literal::hex;
 
+    const ALICE: [u8; 32] =
+        hex!("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+
+    // expected outputs from `sp_crypto_hashing`
+    fn assert_hash(hasher: Hasher, input: &[u8], expected: &[u8]) {
+        assert_eq!(hash(&hasher, input), expected, "{:?} of raw bytes", hasher);
+        let input = format!("0x{}", hex::encode(input));
+        assert_eq!(hash(&hasher, input), expected, "{:?} of hex string", hasher);
+    }
+
     #[test]
     fn hash_blake_hex() {
         let out1 = hash(&Hasher::Blake2_128, "0x68656c6c6f");
         let out2 = hash(&Hasher::Blake2_128, hex!("68656c6c6f"));
         assert_eq!(out1, out2,);
     }
+
+    #[test]
+    fn hash_blake2_128() {
+        let h = || Hasher::Blake2_128;
+        assert_hash(h(), b"", &hex!("cae66941d9efbd404e4d88758ea67670"));
+        assert_hash(h(), b"hello", &hex!("46fb7408d4f285228f4af516ea25851b"));
+        assert_hash(h(), &ALICE, &hex!("de1e86a9a8c739864cf3cc5ec2bea59f"));
+    }
+
+    #[test]
+    fn hash_blake2_256() {
+        let h = || Hasher::Blake2_256;
+        assert_hash(
+            h(),
+            b"",
+            &hex!("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"),
+        );
+        assert_hash(
+            h(),
+            b"hello",
+            &hex!("324dcf027dd4a30a932c441f365a25e86b173defa4b8e58948253471b81b72cf"),
+        );
+        assert_hash(
+            h(),
+            &ALICE,
+            &hex!("2e3fb4c297a84c5cebc0e78257d213d0927ccc7596044c6ba013dd05522aacba"),
+        );
+    }
+
+    #[test]
+    fn hash_blake2_128_concat() {
+        let h = || Hasher::Blake2_128Concat;
+        assert_hash(h(), b"", &hex!("cae66941d9efbd404e4d88758ea67670"));
+        assert_hash(
+            h(),
+            b"hello",
+            &hex!("46fb7408d4f285228f4af516ea25851b68656c6c6f"),
+        );
+        assert_hash(
+            h(),
+            &ALICE,
+            &[hex!("de1e86a9a8c739864cf3cc5ec2bea59f").as_ref(), &ALICE].concat(),
+        );
+    }
+
+    #[test]
+    fn hash_twox_128() {
+        let h = || Hasher::Twox128;
+        assert_hash(h(), b"", &hex!("99e9d85137db46ef4bbea33613baafd5"));
+        assert_hash(h(), b"hello", &hex!("a36d9f887d82c726b2a1d004cb71dd23"));
+        assert_hash(h(), b"System", &hex!("26aa394eea5630e07c48ae0c9558cef7"));
+        assert_hash(h(), b"Account", &hex!("b99d880ec681799c0cf30e8886371da9"));
+        assert_hash(h(), &ALICE, &hex!("518366b5b1bc7c99bae0ba710af1ac66"));
+    }
+
+    #[test]
+    fn hash_twox_256() {
+        let h = || Hasher::Twox256;
+        assert_hash(
+            h(),
+            b"",
+            &hex!("99e9d85137db46ef4bbea33613baafd56f963c64b1f3685a4eb4abd67ff6203a"),
+        );
+        assert_hash(
+            h(),
+            b"hello",
+            &hex!("a36d9f887d82c726b2a1d004cb71dd231fe2fb3bf584fc533914a80e276583e0"),
+        );
+        assert_hash(
+            h(),
+            b"System",
+            &hex!("26aa394eea5630e07c48ae0c9558cef714355510e01e85b83bb4d561945dad84"),
+        );
+        assert_hash(
+            h(),
+            b"Account",
+            &hex!("b99d880ec681799c0cf30e8886371da93f57d85ee2978802071b8b6ddc1f7a8a"),
+        );
+        assert_hash(
+            h(),
+            &ALICE,
+            &hex!("518366b5b1bc7c99bae0ba710af1ac66ecc0fd2f7c15bbe1eb86dbf45c7899e8"),
+        );
+    }
+
+    #[test]
+    fn hash_twox_64_concat() {
+        let h = || Hasher::Twox64Concat;
+        assert_hash(h(), b"", &hex!("99e9d85137db46ef"));
+        assert_hash(h(), b"hello", &hex!("a36d9f887d82c72668656c6c6f"));
+        assert_hash(
+            h(),
+            &ALICE,
+            &[hex!("518366b5b1bc7c99").as_ref(), &ALICE].concat(),
+        );
+    }
+
+    #[test]
+    fn hash_identity() {
+        assert_hash(Hasher::Identity, b"", b"");
+        assert_hash(Hasher::Identity, b"hello", b"hello");
+        assert_hash(Hasher::Identity, &ALICE, &ALICE);
+    }
 }
@@ -6,11 +6,16 @@ use jsonrpc::{
     serde_json::value::to_raw_value,
 };
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 pub use url::Url;
 
-#[derive(Debug)]
-pub struct Backend(Url);
+/// JSONRpc over HTTP, connections are kept in the pool of the client and reused
+/// across requests
+#[derive(Debug, Clone)]
+pub struct Backend {
+    url: Url,
+    client: Client,
+}
 
 impl Backend {
     pub fn new<U>(url: U) -> Self
@@ -18,48 +23,46 @@ impl Backend {
         U: TryInto<Url>,
         <U as TryInto<Url>>::Error: fmt::Debug,
     {
-        Backend(url.try_into().expect("Url"))
+        Self::with_client(url, Client::new())
     }
-}
 
-impl Rpc for Backend {
-    /// HTTP based JSONRpc request expecting an hex encoded result
-    async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+    /// Use an already configured client, e.g. to share its connection pool
+    /// with other backends or to set custom timeouts
+    pub fn with_client<U>(url: U, client: Client) -> Self
     where
-        T: for<'de> Deserialize<'de>,
+        U: TryInto<Url>,
+        <U as TryInto<Url>>::Error: fmt::Debug,
     {
-        log::info!("RPC `{}` to {}", method, &self.0);
+        Backend {
+            url: url.try_into().expect("Url"),
+            client,
+        }
+    }
 
-        let res = Client::new()
-            .post(self.0.to_string())
-            .json(&rpc::Request {
-                id: 1.into(),
-                jsonrpc: Some("2.0"),
-                method,
-                params: &Self::convert_params(params),
-            })
+    async fn post<R>(&self, body: &impl Serialize) -> RpcResult<R>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
+        let res = self
+            .client
+            .post(self.url.clone())
+            .json(body)
             .send()
             .await
             .map_err(|err| rpc::error::Error::Transport(Box::new(err)))?;
 
         let status = res.status();
-        let res = if status.is_success() {
-            res.json::<rpc::Response>()
-                .await
-                .map_err(|err| {
-                    standard_error(
-                        StandardError::ParseError,
-                        Some(to_raw_value(&err.to_string()).unwrap()),
-                    )
-                })?
-                .result::<T>()?
-        } else {
+        if !status.is_success() {
             log::debug!("RPC HTTP status: {}", res.status());
             let err = res
                 .text()
                 .await
                 .unwrap_or_else(|_| status.canonical_reason().expect("to have a message").into());
 
+            // gateways in front of nodes that are down or overloaded
+            if matches!(status.as_u16(), 429 | 502 | 503 | 504) {
+                return Err(rpc::connection_error(&err).into());
+            }
             let err = to_raw_value(&err).expect("error string");
 
             return Err(if status.is_client_error() {
@@ -67,8 +70,99 @@ impl Rpc for Backend {
             } else {
                 standard_error(StandardError::InternalError, Some(err)).into()
             });
+        }
+
+        res.json::<R>().await.map_err(|err| {
+            standard_error(
+                StandardError::ParseError,
+                Some(to_raw_value(&err.to_string()).unwrap()),
+            )
+            .into()
+        })
+    }
+}
+
+impl Rpc for Backend {
+    /// HTTP based JSONRpc request expecting an hex encoded result
+    async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        log::info!("RPC `{}` to {}", method, &self.url);
+
+        self.post::<rpc::Response>(&rpc::Request {
+            id: 1.into(),
+            jsonrpc: Some("2.0"),
+            method,
+            params: &Self::convert_params(params),
+        })
+        .await?
+        .result::<T>()
+    }
+
+    /// All the requests are sent in a single POST as a JSONRpc batch
+    async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        if requests.is_empty() {
+            return Ok(vec![]);
+        }
+        log::info!("RPC batch of {} requests to {}", requests.len(), &self.url);
+
+        let params = requests
+            .iter()
+            .map(|(_, params)| Self::convert_params(params))
+            .collect::<Vec<_>>();
+        let batch = requests
+            .iter()
+            .zip(&params)
+            .enumerate()
+            .map(|(id, ((method, _), params))| rpc::Request {
+                id: id.into(),
+                jsonrpc: Some("2.0"),
+                method,
+                params,
+            })
+            .collect::<Vec<_>>();
+
+        // a node that rejects the whole batch answers with a single response
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum BatchResponse {
+            Batch(Vec<rpc::Response>),
+            Single(rpc::Response),
+        }
+        let responses = match self.post::<BatchResponse>(&batch).await? {
+            BatchResponse::Batch(responses) => responses,
+            BatchResponse::Single(res) => {
+                return Err(res
+                    .result::<()>()
+                    .err()
+                    .unwrap_or(rpc::error::Error::WrongBatchResponseSize))
+            }
         };
+        if responses.len() != requests.len() {
+            return Err(rpc::error::Error::WrongBatchResponseSize);
+        }
+
+        // responses can come in any order, they are matched to their request by id
+        let mut results = (0..requests.len()).map(|_| None).collect::<Vec<_>>();
+        for res in responses {
+            let slot = res
+                .id
+                .as_u64()
+                .and_then(|id| results.get_mut(id as usize))
+                .ok_or_else(|| rpc::error::Error::WrongBatchResponseId(res.id.clone()))?;
+            if slot.is_some() {
+                return Err(rpc::error::Error::BatchDuplicateResponseId(res.id));
+            }
+            *slot = Some(res.result::<T>());
+        }
 
-        Ok(res)
+        Ok(results
+            .into_iter()
+            .map(|res| res.expect("a response for every request"))
+            .collect())
     }
 }
@@ -9,32 +9,39 @@ to automatically encode/decode data into human-readable formats like JSON.
 TODO: rewrite docs for sube 1.0
 */
 
-#[cfg(not(any(feature = "v14")))]
+#[cfg(not(any(feature = "v14", feature = "v15")))]
 compile_error!("Enable one of the metadata versions");
 
 #[macro_use]
 extern crate alloc;
 
+use alloc::{collections::BTreeMap, sync::Arc};
 pub use codec;
-use codec::Encode;
+use codec::{Decode, Encode};
 pub use core::fmt::Display;
 use core::iter::Empty;
 
+pub use call::{Batch, BatchMode, Call, Wrapped, Wrapper};
+pub use era::Era;
+pub use events::{DispatchError, EventRecord};
+pub use extensions::{ExtensionContext, Extensions, PassAuthenticate, SignedExtension};
+pub use extrinsic::{DecodedCall, DecodedExtrinsic, ExtrinsicMode};
 pub use frame_metadata::RuntimeMetadataPrefixed;
-pub use signer::{Bytes, Signer, SignerFn};
+pub use signer::{Bytes, DummySigner, Scheme, Signer, SignerFn};
 
 pub use meta::Metadata;
-#[cfg(feature = "v14")]
+#[cfg(any(feature = "v14", feature = "v15"))]
 pub use scales::{Serializer, Value};
 
 use codec::Compact;
 use core::fmt;
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
 use hasher::hash;
 // use meta::Meta;
 use meta_ext::{self as meta, Meta as _};
 use meta_ext::{KeyValue, StorageKey};
 use prelude::*;
-#[cfg(feature = "v14")]
+#[cfg(any(feature = "v14", feature = "v15"))]
 use scale_info::PortableRegistry;
 use serde::{Deserialize, Serialize};
 pub use serde_json::{json, Value as JsonValue};
@@ -56,8 +63,23 @@ pub mod ws;
 
 pub mod builder;
 pub use builder::SubeBuilder;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub use cache::MetadataCache;
+pub mod call;
+pub mod client;
+pub use client::SubeClient;
+mod era;
+pub mod events;
+pub mod extensions;
+pub mod extrinsic;
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+pub mod failover;
 mod hasher;
 pub mod meta_ext;
+pub mod mock;
+pub use mock::MockBackend;
 mod signer;
 
 #[cfg(any(feature = "http", feature = "http-web", feature = "ws"))]
@@ -78,6 +100,8 @@ async fn query<'m>(
     meta: &'m Metadata,
     path: &str,
     block: Option<u32>,
+    offset: usize,
+    limit: Option<usize>,
 ) -> Result<Response<'m>> {
     let (pallet, item_or_call, mut keys) = parse_uri(path).ok_or(Error::BadInput)?;
     let pallet = meta
@@ -87,7 +111,7 @@ async fn query<'m>(
     if item_or_call == "_constants" {
         let const_name = keys.pop().ok_or(Error::MissingConstantName)?;
         let const_meta = pallet
-            .constants
+            .constants()
             .iter()
             .find(|c| c.name == const_name)
             .ok_or(Error::ConstantNotFound(const_name))?;
@@ -95,49 +119,28 @@ async fn query<'m>(
         return Ok(Response::Value(Value::new(
             const_meta.value.clone(),
             const_meta.ty.id,
-            &meta.types,
+            meta.registry(),
         )));
     }
 
-    if let Ok(key_res) = StorageKey::build_with_registry(&meta.types, pallet, &item_or_call, &keys)
+    if let Ok(key_res) =
+        StorageKey::build_with_registry(meta.registry(), pallet, &item_or_call, &keys)
     {
         if !key_res.is_partial() {
             let res = chain.get_storage_item(key_res.key(), block).await?;
 
             let value = res.map_or(Response::None, |res| {
-                Response::Value(Value::new(res, key_res.ty, &meta.types))
+                Response::Value(Value::new(res, key_res.ty, meta.registry()))
             });
 
             return Ok(value);
         }
 
-        let res = chain.get_keys_paged(key_res.key(), 1000, None).await?;
-        let result = chain.get_storage_items(res, block).await?;
-
-        let value = result
-            .into_iter()
-            .map(|(key, data)| {
-                let key = &key[(key_res.pallet.len() + key_res.call.len())..];
-                let mut offset = 16; // TODO it depends on the hasher used to encode the key, then the size could change
-                let keys = key_res
-                    .args
-                    .iter()
-                    .map(|arg| match arg {
-                        KeyValue::Empty(type_id) | KeyValue::Value((type_id, _, _, _)) => {
-                            let hashed = &key[offset..];
-                            let value = Value::new(hashed.to_vec(), *type_id, &meta.types);
-                            offset += value.size() + 16;
-                            value
-                        }
-                    })
-                    .collect::<Vec<Value<'m>>>();
-
-                let value = data.map(|data| {
-                    Value::new(data.to_vec(), key_res.ty, &meta.types)
-                });
-                (keys, value)
-            })
-            .collect::<Vec<_>>();
+        let value = storage_entries(chain, meta, key_res, block)
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .try_collect()
+            .await?;
 
         Ok(Response::ValueSet(value))
     } else {
@@ -145,9 +148,273 @@ async fn query<'m>(
     }
 }
 
+/// Query several paths of the same chain, the values of full storage keys are fetched
+/// together so backends that support it get them with a single batch of requests.
+/// Other paths like constants or partial keys are queried one by one
+async fn query_many<'m>(
+    chain: &impl Backend,
+    meta: &'m Metadata,
+    paths: &[&str],
+    block: Option<u32>,
+) -> Result<Vec<Response<'m>>> {
+    let keys = paths
+        .iter()
+        .map(|path| {
+            let (pallet, item, keys) = parse_uri(path)?;
+            let pallet = meta.pallet_by_name(&pallet)?;
+            StorageKey::build_with_registry(meta.registry(), pallet, &item, &keys)
+                .ok()
+                .filter(|key| !key.is_partial())
+        })
+        .collect::<Vec<_>>();
+
+    let values = chain
+        .get_storage_items(keys.iter().flatten().map(|k| k.key()).collect(), block)
+        .await?
+        .collect::<BTreeMap<_, _>>();
+
+    let mut responses = Vec::with_capacity(paths.len());
+    for (path, key) in paths.iter().zip(keys) {
+        let response = match key {
+            Some(key) => match values.get(&key.key()).cloned().flatten() {
+                Some(value) => Response::Value(Value::new(value, key.ty, meta.registry())),
+                None => Response::None,
+            },
+            None => query(chain, meta, path, block, 0, None).await?,
+        };
+        responses.push(response);
+    }
+    Ok(responses)
+}
+
+/// Call a runtime API method from a path like `_api/AccountNonceApi/account_nonce/<account>`,
+/// the arguments are encoded with the types declared in the metadata and the output is
+/// decoded with the given type or the declared one. Without type information arguments
+/// are expected to be SCALE encoded hex strings and the output is returned as hex.
+async fn call_api<'m>(
+    chain: &impl Backend,
+    meta: &'m Metadata,
+    path: &str,
+    output_ty: Option<u32>,
+    block: Option<u32>,
+) -> Result<Response<'m>> {
+    let mut path = path.trim_matches('/').split('/').skip(1);
+    let api = path.next().ok_or(Error::BadInput)?;
+    let method = path.next().ok_or(Error::BadInput)?;
+    let args = path.collect::<Vec<_>>();
+    let method_meta = meta.runtime_api(api, method);
+
+    let args = match &method_meta {
+        Some(m) => {
+            if m.inputs.len() != args.len() {
+                return Err(Error::Encode(format!(
+                    "{}_{} expects {} arguments",
+                    api,
+                    method,
+                    m.inputs.len()
+                )));
+            }
+            m.inputs
+                .iter()
+                .zip(args)
+                .map(|(ty, arg)| {
+                    let info = Some((meta.registry(), *ty));
+                    match arg.strip_prefix("0x") {
+                        Some(hex) => {
+                            let bytes = hex::decode(hex).map_err(|_| Error::BadInput)?;
+                            scales::to_vec_with_info(&bytes, info)
+                        }
+                        None => {
+                            let value = serde_json::from_str(arg)
+                                .unwrap_or_else(|_| JsonValue::String(arg.into()));
+                            scales::to_vec_with_info(&value, info)
+                        }
+                    }
+                    .map_err(|e| Error::Encode(e.to_string()))
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+        None => args
+            .iter()
+            .map(|arg| {
+                let hex = arg.strip_prefix("0x").ok_or_else(|| {
+                    Error::Encode(format!(
+                        "Unknown runtime API {}_{}, arguments must be SCALE encoded",
+                        api, method
+                    ))
+                })?;
+                hex::decode(hex).map_err(|_| Error::BadInput)
+            })
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    let res = chain
+        .runtime_call(&format!("{}_{}", api, method), &args.concat(), block)
+        .await?;
+
+    Ok(match output_ty.or(method_meta.map(|m| m.output)) {
+        Some(ty) => Response::Value(Value::new(res, ty, meta.registry())),
+        None => Response::Json(JsonValue::String(format!("0x{}", hex::encode(res)))),
+    })
+}
+
+/// Extrinsics of the block with the given number or of the latest block, decoded
+async fn block<'m>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    number: Option<u32>,
+) -> Result<Response<'m>> {
+    let extrinsics = chain
+        .block_extrinsics(number)
+        .await?
+        .iter()
+        .map(|ext| extrinsic::decode(meta, ext))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Response::Extrinsics(extrinsics))
+}
+
+/// Subscribe to the changes of a storage item or to the special paths
+/// `_heads`, `_heads/finalized` and `_version`, the updates share the metadata
+#[cfg(feature = "ws")]
+async fn watch<'m, R: rpc::Rpc>(
+    chain: &rpc::RpcClient<R>,
+    meta: Arc<Metadata>,
+    path: &str,
+) -> Result<Response<'m>> {
+    let json = |v| Ok::<_, Error>(Response::Json(v));
+    let updates = match path.trim_matches('/') {
+        "_heads" => Updates(chain.subscribe_heads(false).await?.map(json).boxed_local()),
+        "_heads/finalized" => Updates(chain.subscribe_heads(true).await?.map(json).boxed_local()),
+        "_version" => Updates(
+            chain
+                .subscribe_runtime_version()
+                .await?
+                .map(json)
+                .boxed_local(),
+        ),
+        path => {
+            let (pallet, item, keys) = parse_uri(path).ok_or(Error::BadInput)?;
+            let pallet = meta
+                .pallet_by_name(&pallet)
+                .ok_or(Error::PalletNotFound(pallet))?;
+            let key = StorageKey::build_with_registry(meta.registry(), pallet, &item, &keys)?;
+            // the node only notifies about changes of complete keys
+            if key.is_partial() {
+                return Err(Error::BadKey);
+            }
+
+            let ty = key.ty;
+            let changes = chain.subscribe_storage(&[key.key()]).await?;
+            Updates(
+                changes
+                    .map(move |changes| {
+                        let value = changes?.into_iter().next().and_then(|(_, v)| v);
+                        Ok(value.map_or(Response::None, |v| {
+                            Response::Value(Value::shared(v, ty, meta.clone()))
+                        }))
+                    })
+                    .boxed_local(),
+            )
+        }
+    };
+
+    Ok(Response::Updates(updates))
+}
+
+const KEYS_PAGE_SIZE: u16 = 1000;
+
+/// Stream the entries of a storage map under a partial key, the keys are fetched
+/// in pages where each page continues after the last key of the previous one
+pub fn storage_entries<'a, 'm: 'a>(
+    chain: &'a impl Backend,
+    meta: &'m Metadata,
+    key: StorageKey,
+    block: Option<u32>,
+) -> impl Stream<Item = Result<(Vec<MapKey<'m>>, Option<Value<'m>>)>> + 'a {
+    let prefix = key.key();
+
+    // the state is the start key of the next page, `None` when there are no more pages
+    let pages = stream::try_unfold(Some(None), move |start_key: Option<Option<RawKey>>| {
+        let prefix = prefix.clone();
+        async move {
+            let Some(start_key) = start_key else {
+                return Ok(None);
+            };
+            let keys = chain
+                .get_keys_paged(prefix, KEYS_PAGE_SIZE, start_key, block)
+                .await?;
+            if keys.is_empty() {
+                return Ok(None);
+            }
+            let next = if keys.len() < KEYS_PAGE_SIZE.into() {
+                None
+            } else {
+                keys.last().cloned().map(Some)
+            };
+
+            let entries = chain.get_storage_items(keys, block).await?;
+            let entries = stream::iter(entries.map(Ok::<_, Error>).collect::<Vec<_>>());
+            Ok::<_, Error>(Some((entries, next)))
+        }
+    });
+
+    pages
+        .try_flatten()
+        .map(move |entry| entry.and_then(|(k, data)| decode_entry(meta.registry(), &key, &k, data)))
+}
+
+/// Recover the keys of a storage map entry from its raw key, every key is preceded
+/// by its hash and only hashers that concatenate the key allow decoding it
+fn decode_entry<'m>(
+    registry: &'m PortableRegistry,
+    key_res: &StorageKey,
+    key: &[u8],
+    data: Option<RawValue>,
+) -> Result<(Vec<MapKey<'m>>, Option<Value<'m>>)> {
+    let mut key = key
+        .get((key_res.pallet.len() + key_res.call.len())..)
+        .ok_or(Error::BadKey)?;
+
+    let keys = key_res
+        .args
+        .iter()
+        .map(|arg| {
+            let (KeyValue::Empty(type_id, hasher) | KeyValue::Value((type_id, _, _, hasher))) = arg;
+            let (hash_len, concat) = hasher::hash_len(hasher);
+            let hash = key.get(..hash_len).ok_or(Error::BadKey)?;
+            key = &key[hash_len..];
+
+            if !concat {
+                return Ok(MapKey::Hash(hash.to_vec()));
+            }
+            let size = extrinsic::encoded_size(registry, *type_id, key).ok_or(Error::BadKey)?;
+            let (value, rest) = key.split_at(size);
+            key = rest;
+            Ok(MapKey::Value(Value::new(
+                value.to_vec(),
+                *type_id,
+                registry,
+            )))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let value = data.map(|data| Value::new(data, key_res.ty, registry));
+    Ok((keys, value))
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ExtrinsicBody<Body> {
     pub nonce: Option<u64>,
+    /// Number of blocks the extrinsic is valid for, immortal when not set
+    pub mortality: Option<u64>,
+    pub tip: Option<u128>,
+    /// Asset used to pay the fees instead of the native token
+    pub fee_asset: Option<JsonValue>,
+    /// Hash of the genesis block of the chain, fetched from the chain when not set
+    pub genesis_hash: Option<[u8; 32]>,
+    /// Runtime versions the extrinsic is signed for, read from the metadata when not set
+    pub spec_version: Option<u32>,
+    pub transaction_version: Option<u32>,
     pub body: Body,
 }
 
@@ -176,25 +443,266 @@ async fn submit<'m, V>(
     path: &str,
     tx_data: ExtrinsicBody<V>,
     signer: impl Signer,
+    extensions: &Extensions<'_>,
+    level: ConfirmationLevel,
+) -> Result<Response<'m>>
+where
+    V: Call + core::fmt::Debug,
+{
+    let extrinsic = build_extrinsic(&chain, meta, path, tx_data, signer, extensions).await?;
+    broadcast(chain, extrinsic, level).await
+}
+
+/// Send an already signed extrinsic to the chain, it can be one assembled
+/// with [`SigningPayload::attach_signature`] somewhere else
+pub async fn broadcast<'m>(
+    chain: impl Backend,
+    extrinsic: impl AsRef<[u8]>,
+    level: ConfirmationLevel,
+) -> Result<Response<'m>> {
+    if level == ConfirmationLevel::Submitted {
+        chain.submit(extrinsic).await?;
+        return Ok(Response::Void);
+    }
+
+    let tx = chain.submit_and_watch(extrinsic, level).await?;
+    Ok(Response::Tx(tx))
+}
+
+/// Build the extrinsic `submit` would send but with a dummy signature
+/// to find out the fees it would pay without asking the signer to sign
+async fn estimate_fee<'m, V>(
+    chain: impl Backend,
+    meta: &'m Metadata,
+    path: &str,
+    tx_data: ExtrinsicBody<V>,
+    signer: impl Signer,
+    extensions: &Extensions<'_>,
 ) -> Result<Response<'m>>
 where
-    V: serde::Serialize + core::fmt::Debug,
+    V: Call + core::fmt::Debug,
+{
+    let extrinsic =
+        build_extrinsic(&chain, meta, path, tx_data, DummySigner(signer), extensions).await?;
+    let len = u32::try_from(extrinsic.len()).expect("extrinsic size expected to be <4GB");
+    let args = [extrinsic, len.encode()].concat();
+
+    let mut info: FeeInfo =
+        payment_api(&chain, meta, "TransactionPaymentApi", "query_info", &args).await?;
+    info.details = Some(
+        payment_api(
+            &chain,
+            meta,
+            "TransactionPaymentApi",
+            "query_fee_details",
+            &args,
+        )
+        .await?,
+    );
+
+    Ok(Response::Fee(info))
+}
+
+/// Call a runtime API method of the transaction payment pallet and decode its output
+/// with the type the metadata declares for it. Without runtime API metadata the output
+/// is assumed to have the multi-dimensional weight and balances of 128 bits
+pub(crate) async fn payment_api<T>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    api: &str,
+    method: &str,
+    args: &[u8],
+) -> Result<T>
+where
+    T: Decode + for<'de> Deserialize<'de>,
+{
+    let output = chain
+        .runtime_call(&format!("{}_{}", api, method), args, None)
+        .await?;
+    let Some(method) = meta.runtime_api(api, method) else {
+        return T::decode(&mut output.as_slice()).map_err(Error::Decode);
+    };
+
+    let mut output: JsonValue = Value::new(output, method.output, meta.registry()).into();
+    // runtimes before multi-dimensional weights only have the computation time
+    if let Some(ref_time) = output.get("weight").filter(|w| w.is_number()).cloned() {
+        output["weight"] = json!({ "ref_time": ref_time, "proof_size": 0 });
+    }
+    serde_json::from_value(output)
+        .map_err(|_| Error::Decode("unexpected transaction payment info".into()))
+}
+
+async fn build_extrinsic<V>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    path: &str,
+    tx_data: ExtrinsicBody<V>,
+    signer: impl Signer,
+    extensions: &Extensions<'_>,
+) -> Result<Vec<u8>>
+where
+    V: Call + core::fmt::Debug,
+{
+    let account = signer.account();
+    let payload = signing_payload(
+        chain,
+        meta,
+        path,
+        tx_data,
+        account.as_ref(),
+        signer.scheme(),
+        extensions,
+    )
+    .await?;
+    let signature = signer.sign(payload.message()).await?;
+    Ok(payload.attach_signature(signature))
+}
+
+/// The parts of an extrinsic that are known before it's signed. It can be
+/// SCALE encoded to sign it elsewhere, e.g. with an air-gapped wallet, and
+/// assembled into the extrinsic once the signature is known
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct SigningPayload {
+    /// Header of a signed extrinsic in the format version of the runtime
+    pub header: u8,
+    /// Account of the signer encoded as the address type of the runtime
+    pub address: Vec<u8>,
+    /// Variant of the runtime's signature type for the scheme of the signer
+    pub signature_variant: Option<u8>,
+    pub call: Vec<u8>,
+    pub extra: Vec<u8>,
+    pub additional: Vec<u8>,
+}
+
+impl SigningPayload {
+    /// Bytes the signer has to sign, payloads longer than 256 bytes are hashed
+    pub fn message(&self) -> Vec<u8> {
+        let payload = [
+            self.call.as_slice(),
+            self.extra.as_slice(),
+            self.additional.as_slice(),
+        ]
+        .concat();
+
+        if payload.len() > 256 {
+            hash(&meta::Hasher::Blake2_256, &payload[..])
+        } else {
+            payload
+        }
+    }
+
+    /// Assemble the length prefixed extrinsic ready to be broadcast
+    pub fn attach_signature(&self, signature: impl AsRef<[u8]>) -> Vec<u8> {
+        let encoded_inner = [
+            &[self.header][..],
+            // signer
+            &self.address,
+            // signature
+            self.signature_variant.as_slice(),
+            signature.as_ref(),
+            // extra
+            &self.extra,
+            // call data
+            &self.call,
+        ]
+        .concat();
+
+        length_prefixed(encoded_inner)
+    }
+}
+
+fn length_prefixed(extrinsic: Vec<u8>) -> Vec<u8> {
+    let len = Compact(u32::try_from(extrinsic.len()).expect("extrinsic size expected to be <4GB"));
+    [len.encode(), extrinsic].concat()
+}
+
+/// Encode the call and signed extensions of an extrinsic sent by `account`
+/// which signs it with the given `scheme`.
+/// The chain is only queried for what `tx_data` doesn't provide, with the nonce
+/// and genesis hash given an immortal extrinsic can be built with an [`Offline`] backend
+pub async fn signing_payload<V>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    path: &str,
+    tx_data: ExtrinsicBody<V>,
+    account: &[u8],
+    scheme: Scheme,
+    extensions: &Extensions<'_>,
+) -> Result<SigningPayload>
+where
+    V: Call + core::fmt::Debug,
+{
+    let header = extrinsic::header(meta, ExtrinsicMode::Signed)?;
+    log::debug!("tx_data: {:?}", tx_data);
+    let call = tx_data
+        .body
+        .encode_call(chain, meta, path, Some(account))
+        .await?;
+
+    log::debug!("from_account: {:?}", hex::encode(account));
+    let (extra, additional) =
+        encode_extensions(chain, meta, &tx_data, Some(account), extensions).await?;
+
+    Ok(SigningPayload {
+        header,
+        address: extrinsic::encode_address(meta, account)?,
+        signature_variant: extrinsic::signature_variant(meta, scheme)?,
+        call,
+        extra,
+        additional,
+    })
+}
+
+/// Build an extrinsic that isn't signed by its sender, a bare `Unsigned` one
+/// only has the call while a `General` transaction includes the values of the
+/// transaction extensions that authorize it. Bare extrinsics don't need the chain
+pub async fn unsigned_extrinsic<V>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    path: &str,
+    tx_data: ExtrinsicBody<V>,
+    mode: ExtrinsicMode,
+    extensions: &Extensions<'_>,
+) -> Result<Vec<u8>>
+where
+    V: Call + core::fmt::Debug,
+{
+    let header = extrinsic::header(meta, mode)?;
+    log::debug!("tx_data: {:?}", tx_data);
+    let call = tx_data.body.encode_call(chain, meta, path, None).await?;
+
+    let inner = match mode {
+        ExtrinsicMode::Signed => return Err(Error::BadInput),
+        ExtrinsicMode::Unsigned => [vec![header], call].concat(),
+        ExtrinsicMode::General => {
+            let (extra, _) = encode_extensions(chain, meta, &tx_data, None, extensions).await?;
+            // version of the transaction extensions, only one is declared by the runtime
+            [vec![header, 0], extra, call].concat()
+        }
+    };
+
+    Ok(length_prefixed(inner))
+}
+
+/// Encode a call like `balances/transfer_keep_alive` with its JSON arguments
+fn encode_call<V>(meta: &Metadata, path: &str, body: &V) -> Result<Vec<u8>>
+where
+    V: serde::Serialize + ?Sized,
 {
     let (pallet, item_or_call, _keys) = parse_uri(path).ok_or(Error::BadInput)?;
     let pallet = meta
         .pallet_by_name(&pallet)
         .ok_or(Error::PalletNotFound(pallet))?;
-    let calls_ty = pallet.calls.as_ref().ok_or(Error::CallNotFound)?.ty.id;
+    let calls_ty = pallet.calls().ok_or(Error::CallNotFound)?.ty.id;
 
     log::debug!("calls_ty: {:?}", calls_ty);
 
-    let type_registry = &meta.types;
+    let type_registry = meta.registry();
 
-    let mut encoded_call = vec![pallet.index];
+    let mut encoded_call = vec![pallet.index()];
 
-    log::debug!("tx_data: {:?}", tx_data);
     let json = &json!({
-        &item_or_call.to_lowercase(): &tx_data.body
+        &item_or_call.to_lowercase(): body
     });
     log::debug!("json_body: {:?}", &json);
 
@@ -203,144 +711,280 @@ where
 
     encoded_call.extend(&call_data);
 
-    let from_account = signer.account();
-    log::debug!("from_account: {:?}", hex::encode(from_account.as_ref()));
+    Ok(encoded_call)
+}
 
-    let extra_params = {
-        // ImmortalEra
-        let era = 0u8;
+/// Extra data and additional signed data of the signed extensions, values not
+/// given in `tx_data` are fetched from the chain. The nonce is only looked up
+/// for extrinsics sent by an account
+async fn encode_extensions<V>(
+    chain: &impl Backend,
+    meta: &Metadata,
+    tx_data: &ExtrinsicBody<V>,
+    account: Option<&[u8]>,
+    extensions: &Extensions<'_>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let genesis_block: Vec<u8> = match tx_data.genesis_hash {
+        Some(hash) => hash.into(),
+        None => chain.block_info(Some(0u32)).await?.into(),
+    };
 
-        // Impl. Note: in a real-world use case, you should store your account's nonce somewhere else
-        let nonce = {
-            if let Some(nonce) = tx_data.nonce {
-                Ok(nonce)
+    let (era, checkpoint) = match tx_data.mortality {
+        Some(period) => {
+            let current = chain.block_info(None).await?;
+            let era = Era::mortal(period, current.number);
+            let birth = era.birth(current.number);
+            let checkpoint: Vec<u8> = if birth == current.number {
+                current.into()
             } else {
-                let response = query(
-                    &chain,
-                    meta,
-                    &format!("system/account/0x{}", hex::encode(from_account.as_ref())),
-                    None,
-                )
-                .await?;
-
-                match response {
-                    Response::Value(value) => {
-                        let str = serde_json::to_string(&value).expect("wrong account info");
-                        let account_info: AccountInfo =
-                            serde_json::from_str(&str).expect("it must serialize");
-                        Ok(account_info.nonce)
-                    }
-                    Response::None => {
-                        log::warn!("account not found");
-                        Ok(0)
+                chain.block_info(Some(birth as u32)).await?.into()
+            };
+            (era, checkpoint)
+        }
+        None => (Era::Immortal, genesis_block.clone()),
+    };
+    log::debug!("era: {:?}", era);
+
+    // Impl. Note: in a real-world use case, you should store your account's nonce somewhere else
+    let nonce = {
+        if let Some(nonce) = tx_data.nonce {
+            Ok(nonce)
+        } else if let Some(account) = account {
+            let response = query(
+                chain,
+                meta,
+                &format!("system/account/0x{}", hex::encode(account)),
+                None,
+                0,
+                None,
+            )
+            .await?;
+
+            match response {
+                Response::Value(value) => {
+                    let bad_account = || Error::Decode("unexpected account info".into());
+                    // the value is read as its type when serialized, it must have its size
+                    if value.size() != value.as_ref().len() {
+                        return Err(bad_account());
                     }
-                    _ => Err(Error::AccountNotFound),
+                    let account_info: AccountInfo = serde_json::to_value(&value)
+                        .and_then(serde_json::from_value)
+                        .map_err(|_| bad_account())?;
+                    Ok(account_info.nonce)
                 }
+                Response::None => {
+                    log::warn!("account not found");
+                    Ok(0)
+                }
+                _ => Err(Error::AccountNotFound),
             }
-        }?;
-
-        let tip: u128 = 0;
+        } else {
+            Ok(0)
+        }
+    }?;
+
+    let (spec_version, transaction_version) =
+        match (tx_data.spec_version, tx_data.transaction_version) {
+            (Some(spec_version), Some(transaction_version)) => (spec_version, transaction_version),
+            (spec, tx) => {
+                let (spec_version, transaction_version) = runtime_version(meta)?;
+                (
+                    spec.unwrap_or(spec_version),
+                    tx.unwrap_or(transaction_version),
+                )
+            }
+        };
+
+    extensions.encode(
+        meta,
+        &ExtensionContext {
+            registry: meta.registry(),
+            nonce,
+            tip: tx_data.tip.unwrap_or_default(),
+            fee_asset: tx_data.fee_asset.as_ref(),
+            era,
+            spec_version,
+            transaction_version,
+            genesis_hash: &genesis_block,
+            checkpoint: &checkpoint,
+        },
+    )
+}
 
-        [
-            vec![era],
-            Compact(nonce).encode(),
-            Compact(tip).encode(),
-            vec![0x00u8], // chain extension for kreivo
-        ]
-        .concat()
-    };
+/// Spec and transaction versions of the runtime the metadata belongs to
+fn runtime_version(meta: &Metadata) -> Result<(u32, u32)> {
+    let data = meta
+        .pallet_by_name("System")
+        .ok_or(Error::PalletNotFound(String::from("System")))?
+        .constants()
+        .iter()
+        .find(|c| c.name == "Version")
+        .ok_or(Error::ConstantNotFound("System_Version".into()))?;
 
-    let additional_params = {
-        // Error: Still failing to deserialize the const
-        let metadata = meta;
+    let chain_value: JsonValue = Value::new(data.value.clone(), data.ty.id, meta.registry()).into();
 
-        let mut constants = metadata
-            .pallet_by_name("System")
-            .ok_or(Error::PalletNotFound(String::from("System")))?
-            .constants
-            .clone()
-            .into_iter();
+    let iter = chain_value
+        .as_object()
+        .ok_or(Error::ConstantNotFound("System_Version".into()))?;
 
-        let data = constants
-            .find(|c| c.name == "Version")
-            .ok_or(Error::ConstantNotFound("System_Version".into()))?;
+    let transaction_version = iter.get("transaction_version").ok_or(Error::Mapping(
+        "System_Version.transaction_version not found in transaction version".into(),
+    ))?;
 
-        let chain_value: JsonValue = Value::new(data.value, data.ty.id, &meta.types).into();
+    let spec_version = iter.get("spec_version").ok_or(Error::Mapping(
+        "System_Version.spec_version not found in transaction version".into(),
+    ))?;
 
-        let iter = chain_value
-            .as_object()
-            .ok_or(Error::ConstantNotFound("System_Version".into()))?;
+    let spec_version = spec_version.as_u64().ok_or(Error::Mapping(
+        "System_Version.spec_version is not a number".into(),
+    ))? as u32;
 
-        let transaction_version = iter.get("transaction_version").ok_or(Error::Mapping(
-            "System_Version.transaction_version not found in transaction version".into(),
-        ))?;
+    let transaction_version = transaction_version.as_u64().ok_or(Error::Mapping(
+        "System_Version.transaction_version is not a number".into(),
+    ))? as u32;
 
-        let spec_version = iter.get("spec_version").ok_or(Error::Mapping(
-            "System_Version.spec_version not found in transaction version".into(),
-        ))?;
-        // chain_version
+    Ok((spec_version, transaction_version))
+}
 
-        let spec_version = spec_version.as_u64().ok_or(Error::Mapping(
-            "System_Version.spec_version is not a number".into(),
-        ))? as u32;
+/// Status of an extrinsic in the transaction pool of the node
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TxStatus {
+    Future,
+    Ready,
+    Broadcast(Vec<String>),
+    InBlock(String),
+    Retracted(String),
+    FinalityTimeout(String),
+    Finalized(String),
+    Usurped(String),
+    Dropped,
+    Invalid,
+}
 
-        let transaction_version = transaction_version.as_u64().ok_or(Error::Mapping(
-            "System_Version.transaction_version is not a number".into(),
-        ))? as u32;
+impl TxStatus {
+    /// The extrinsic has reached the given level of confirmation
+    pub fn reached(&self, level: ConfirmationLevel) -> bool {
+        match level {
+            ConfirmationLevel::Submitted => true,
+            ConfirmationLevel::Included => {
+                matches!(self, TxStatus::InBlock(_) | TxStatus::Finalized(_))
+            }
+            ConfirmationLevel::Finalized => matches!(self, TxStatus::Finalized(_)),
+        }
+    }
 
-        let genesis_block: Vec<u8> = chain.block_info(Some(0u32)).await?.into();
+    /// No more updates are expected after a final status
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxStatus::Finalized(_)
+                | TxStatus::FinalityTimeout(_)
+                | TxStatus::Usurped(_)
+                | TxStatus::Dropped
+                | TxStatus::Invalid
+        )
+    }
+}
 
-        [
-            spec_version.to_le_bytes().to_vec(),
-            transaction_version.to_le_bytes().to_vec(),
-            genesis_block.clone(),
-            genesis_block.clone(),
-        ]
-        .concat()
-    };
+/// How far an extrinsic has to go before its submission is considered done
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmationLevel {
+    /// Accepted by the node
+    #[default]
+    Submitted,
+    /// Included in a block
+    Included,
+    /// Included in a finalized block
+    Finalized,
+}
 
-    let signature_payload = [
-        encoded_call.clone(),
-        extra_params.clone(),
-        additional_params.clone(),
-    ]
-    .concat();
+#[derive(Serialize, Debug, Clone)]
+pub struct TxInfo {
+    pub hash: String,
+    pub status: TxStatus,
+}
 
-    let payload = if signature_payload.len() > 256 {
-        hash(&meta::Hasher::Blake2_256, &signature_payload[..])
-    } else {
-        signature_payload
-    };
+/// Fees an extrinsic would pay along with its weight and dispatch class
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeeInfo {
+    pub weight: Weight,
+    pub class: DispatchClass,
+    pub partial_fee: u128,
+    /// Breakdown of the fees when estimating the fees of an extrinsic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<FeeDetails>,
+}
 
-    let signature = signer.sign(payload).await?;
+/// Fees of an extrinsic as returned by `TransactionPaymentApi_query_fee_details`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeDetails {
+    /// Fees for including the extrinsic in a block, unsigned extrinsics don't pay them
+    pub inclusion_fee: Option<InclusionFee>,
+    pub tip: u128,
+}
 
-    let extrinsic_call = {
-        let encoded_inner = [
-            // header: "is signed" (1 byte) + transaction protocol version (7 bytes)
-            vec![0b10000000 + 4u8],
-            // signer
-            vec![0x00],
-            from_account.as_ref().to_vec(),
-            // signature
-            [vec![0x01], signature.as_ref().to_vec()].concat(),
-            // extra
-            extra_params,
-            // call data
-            encoded_call,
-        ]
-        .concat();
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InclusionFee {
+    pub base_fee: u128,
+    pub len_fee: u128,
+    pub adjusted_weight_fee: u128,
+}
 
-        let len = Compact(
-            u32::try_from(encoded_inner.len()).expect("extrinsic size expected to be <4GB"),
-        )
-        .encode();
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weight {
+    pub ref_time: u64,
+    pub proof_size: u64,
+}
 
-        [len, encoded_inner].concat()
-    };
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all(serialize = "lowercase"))]
+pub enum DispatchClass {
+    Normal,
+    Operational,
+    Mandatory,
+}
 
-    chain.submit(&extrinsic_call).await?;
+// Decoded from the `RuntimeDispatchInfo` returned by `TransactionPaymentApi_query_info`
+// assuming the multi-dimensional weight and balances of 128 bits
+impl Decode for FeeInfo {
+    fn decode<I: codec::Input>(input: &mut I) -> core::result::Result<Self, codec::Error> {
+        let weight = Weight {
+            ref_time: Compact::<u64>::decode(input)?.0,
+            proof_size: Compact::<u64>::decode(input)?.0,
+        };
+        let class = match input.read_byte()? {
+            0 => DispatchClass::Normal,
+            1 => DispatchClass::Operational,
+            2 => DispatchClass::Mandatory,
+            _ => return Err("unknown dispatch class".into()),
+        };
+        let partial_fee = u128::decode(input)?;
+
+        Ok(FeeInfo {
+            weight,
+            class,
+            partial_fee,
+            details: None,
+        })
+    }
+}
 
-    Ok(Response::Void)
+impl Decode for FeeDetails {
+    fn decode<I: codec::Input>(input: &mut I) -> core::result::Result<Self, codec::Error> {
+        let inclusion_fee = Option::<(u128, u128, u128)>::decode(input)?.map(
+            |(base_fee, len_fee, adjusted_weight_fee)| InclusionFee {
+                base_fee,
+                len_fee,
+                adjusted_weight_fee,
+            },
+        );
+        Ok(FeeDetails {
+            inclusion_fee,
+            tip: u128::decode(input)?,
+        })
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -349,9 +993,97 @@ pub enum Response<'m> {
     Void,
     None,
     Value(scales::Value<'m>),
-    ValueSet(Vec<(Vec<scales::Value<'m>>, Option<scales::Value<'m>>)>),
-    Meta(&'m Metadata),
-    Registry(&'m PortableRegistry),
+    ValueSet(Vec<(Vec<MapKey<'m>>, Option<scales::Value<'m>>)>),
+    Meta(Arc<Metadata>),
+    Registry(PortableRegistry),
+    Tx(TxInfo),
+    Events(Vec<EventRecord>),
+    Extrinsics(Vec<DecodedExtrinsic>),
+    Fee(FeeInfo),
+    Json(JsonValue),
+    #[cfg(feature = "ws")]
+    #[serde(skip)]
+    Updates(Updates),
+}
+
+impl Response<'_> {
+    /// The same response with its values keeping the metadata they were
+    /// decoded with alive instead of borrowing it
+    pub(crate) fn into_shared<'a>(self, meta: &Arc<Metadata>) -> Response<'a> {
+        let shared = |v: Value| v.into_shared(meta.clone());
+        match self {
+            Response::Value(v) => Response::Value(shared(v)),
+            Response::ValueSet(entries) => Response::ValueSet(
+                entries
+                    .into_iter()
+                    .map(|(keys, value)| {
+                        let keys = keys
+                            .into_iter()
+                            .map(|k| match k {
+                                MapKey::Value(v) => MapKey::Value(shared(v)),
+                                MapKey::Hash(h) => MapKey::Hash(h),
+                            })
+                            .collect();
+                        (keys, value.map(shared))
+                    })
+                    .collect(),
+            ),
+            Response::Void => Response::Void,
+            Response::None => Response::None,
+            Response::Meta(m) => Response::Meta(m),
+            Response::Registry(r) => Response::Registry(r),
+            Response::Tx(tx) => Response::Tx(tx),
+            Response::Events(events) => Response::Events(events),
+            Response::Extrinsics(extrinsics) => Response::Extrinsics(extrinsics),
+            Response::Fee(fee) => Response::Fee(fee),
+            Response::Json(value) => Response::Json(value),
+            #[cfg(feature = "ws")]
+            Response::Updates(updates) => Response::Updates(updates),
+        }
+    }
+}
+
+/// Key of a storage map entry, keys hashed with an opaque hasher can't be
+/// decoded and are left as the raw hash
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum MapKey<'m> {
+    Value(scales::Value<'m>),
+    #[serde(serialize_with = "util::serialize_hex")]
+    Hash(Vec<u8>),
+}
+
+impl Encode for MapKey<'_> {
+    fn encode_to<T: codec::Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            MapKey::Value(v) => v.encode_to(dest),
+            MapKey::Hash(h) => dest.write(h),
+        }
+    }
+}
+
+/// Stream of responses pushed by the node for a subscription,
+/// it's unsubscribed once dropped
+#[cfg(feature = "ws")]
+pub struct Updates(futures_util::stream::LocalBoxStream<'static, Result<Response<'static>>>);
+
+#[cfg(feature = "ws")]
+impl fmt::Debug for Updates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Updates")
+    }
+}
+
+#[cfg(feature = "ws")]
+impl futures_util::Stream for Updates {
+    type Item = Result<Response<'static>>;
+
+    fn poll_next(
+        mut self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Self::Item>> {
+        self.0.as_mut().poll_next(cx)
+    }
 }
 
 impl From<Response<'_>> for Vec<u8> {
@@ -363,6 +1095,13 @@ impl From<Response<'_>> for Vec<u8> {
             Response::Registry(r) => r.encode(),
             Response::ValueSet(r) => r.encode(),
             Response::Void => vec![],
+            Response::Tx(tx) => hex::decode(tx.hash.trim_start_matches("0x")).unwrap_or_default(),
+            Response::Events(events) => serde_json::to_vec(&events).unwrap_or_default(),
+            Response::Extrinsics(extrinsics) => serde_json::to_vec(&extrinsics).unwrap_or_default(),
+            Response::Fee(fee) => serde_json::to_vec(&fee).unwrap_or_default(),
+            Response::Json(value) => serde_json::to_vec(&value).unwrap_or_default(),
+            #[cfg(feature = "ws")]
+            Response::Updates(_) => vec![],
         }
     }
 }
@@ -417,19 +1156,43 @@ pub trait Backend {
             .ok_or(Error::StorageKeyNotFound)
     }
 
+    /// Keys with the given prefix starting after `start_key` when given,
+    /// as they are at the given block or the latest one
     async fn get_keys_paged(
         &self,
-        from: RawKey,
+        prefix: RawKey,
         size: u16,
-        to: Option<RawKey>,
-    ) -> crate::Result<Vec<RawValue>>;
+        start_key: Option<RawKey>,
+        block: Option<u32>,
+    ) -> crate::Result<Vec<RawKey>>;
 
     /// Send a signed extrinsic to the blockchain
     async fn submit(&self, ext: impl AsRef<[u8]>) -> Result<()>;
 
+    /// Send a signed extrinsic and wait for it to reach the given confirmation level
+    async fn submit_and_watch(
+        &self,
+        _ext: impl AsRef<[u8]>,
+        _level: ConfirmationLevel,
+    ) -> Result<TxInfo> {
+        Err(Error::Platform(
+            "Watching extrinsics is not supported by the backend".into(),
+        ))
+    }
+
     async fn metadata(&self) -> Result<Metadata>;
 
     async fn block_info(&self, at: Option<u32>) -> Result<meta::BlockInfo>;
+
+    /// Call a method of a runtime API with its SCALE encoded arguments
+    async fn runtime_call(&self, method: &str, args: &[u8], block: Option<u32>) -> Result<Vec<u8>>;
+
+    /// Extrinsics included in the block with the given number or in the latest block
+    async fn block_extrinsics(&self, _at: Option<u32>) -> Result<Vec<Vec<u8>>> {
+        Err(Error::Platform(
+            "Fetching blocks is not supported by the backend".into(),
+        ))
+    }
 }
 
 /// A Dummy backend for offline querying of metadata
@@ -446,9 +1209,10 @@ impl Backend for Offline {
 
     async fn get_keys_paged(
         &self,
-        _from: RawKey,
+        _prefix: RawKey,
         _size: u16,
-        _to: Option<RawKey>,
+        _start_key: Option<RawKey>,
+        _block: Option<u32>,
     ) -> crate::Result<Vec<RawKey>> {
         Err(Error::ChainUnavailable)
     }
@@ -465,6 +1229,14 @@ impl Backend for Offline {
     async fn block_info(&self, _: Option<u32>) -> Result<meta::BlockInfo> {
         Err(Error::ChainUnavailable)
     }
+
+    async fn runtime_call(&self, _: &str, _: &[u8], _: Option<u32>) -> Result<Vec<u8>> {
+        Err(Error::ChainUnavailable)
+    }
+
+    async fn block_extrinsics(&self, _: Option<u32>) -> Result<Vec<Vec<u8>>> {
+        Err(Error::ChainUnavailable)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -492,12 +1264,20 @@ pub enum Error {
     CantDecodeRawQueryResponse,
     CantFindMethodInPallet,
     BadBlockNumber,
+    UnknownExtension(String),
+    ExtrinsicDropped(TxStatus),
+    UnsupportedExtrinsic(ExtrinsicMode),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Node(e) => write!(f, "{:}", e),
+            Self::UnsupportedExtrinsic(mode) => write!(
+                f,
+                "{:?} extrinsics are not supported by the extrinsic versions of the metadata",
+                mode
+            ),
             _ => write!(f, "{:?}", self),
         }
     }
@@ -508,3 +1288,793 @@ impl std::error::Error for Error {}
 
 #[cfg(feature = "no_std")]
 impl core::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::fixtures::{kreivo, kreivo_v5, kusama};
+    use hex_literal::hex;
+
+    const ALICE: [u8; 32] =
+        hex!("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+
+    /// Body of an immortal extrinsic for a chain whose genesis hash is `[1; 32]`
+    fn tx_data<V>(nonce: Option<u64>, body: V) -> ExtrinsicBody<V> {
+        ExtrinsicBody {
+            nonce,
+            mortality: None,
+            tip: None,
+            fee_asset: None,
+            genesis_hash: Some([1; 32]),
+            spec_version: None,
+            transaction_version: None,
+            body,
+        }
+    }
+
+    fn entry_keys(meta: &Metadata, pallet: &str, item: &str, raw_key: &[u8]) -> JsonValue {
+        let pallet = meta.pallet_by_name(pallet).expect("pallet exists");
+        let key = StorageKey::build_with_registry(meta.registry(), pallet, item, &[] as &[&str])
+            .expect("storage item exists");
+        let (keys, _) = decode_entry(meta.registry(), &key, raw_key, None).expect("valid key");
+        serde_json::to_value(keys).expect("serializable keys")
+    }
+
+    #[async_std::test]
+    async fn sign_offline_and_broadcast_later() {
+        let meta = kreivo();
+        let chain = Offline(meta.clone());
+        let remark = json!({ "remark": ["1", "2", "3", "4"] });
+        let extensions = Extensions::default();
+
+        let payload = signing_payload(
+            &chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(3), &remark),
+            &ALICE,
+            Scheme::Sr25519,
+            &extensions,
+        )
+        .await
+        .expect("nothing to fetch from the chain");
+        assert_eq!(payload.address, [&[0x00][..], &ALICE].concat());
+        assert_eq!(payload.signature_variant, Some(1));
+        assert!(payload.additional.windows(32).any(|w| w == [1; 32]));
+        assert_eq!(
+            payload.message(),
+            [&payload.call[..], &payload.extra, &payload.additional].concat()
+        );
+        let encoded = payload.encode();
+        assert_eq!(SigningPayload::decode(&mut &encoded[..]).unwrap(), payload);
+
+        let extrinsic = payload.attach_signature([7; 64]);
+        let len = Compact::<u32>::decode(&mut &extrinsic[..]).unwrap().0 as usize;
+        let inner = &extrinsic[extrinsic.len() - len..];
+        assert_eq!(inner[..2], [0x84, 0x00]);
+        assert_eq!(inner[2..34], ALICE);
+        assert_eq!(inner[34..99], [&[1][..], &[7; 64]].concat()[..]);
+        assert!(inner.ends_with(&payload.call));
+
+        // the nonce can't be looked up without a chain
+        assert!(matches!(
+            signing_payload(
+                &chain,
+                &meta,
+                "system/remark",
+                tx_data(None, &remark),
+                &ALICE,
+                Scheme::Sr25519,
+                &extensions
+            )
+            .await,
+            Err(Error::ChainUnavailable)
+        ));
+
+        let node = MockBackend::new(meta.clone());
+        let res = broadcast(node.clone(), &extrinsic, ConfirmationLevel::Submitted).await;
+        assert!(matches!(res, Ok(Response::Void)));
+        assert_eq!(node.extrinsics().await, vec![extrinsic]);
+    }
+
+    #[async_std::test]
+    async fn tip_and_fee_asset() {
+        let meta = kreivo();
+        let chain = Offline(meta.clone());
+        let remark = json!({ "remark": ["1"] });
+        let extra = |tx_data| async {
+            signing_payload(
+                &chain,
+                &meta,
+                "system/remark",
+                tx_data,
+                &ALICE,
+                Scheme::Sr25519,
+                &Extensions::default(),
+            )
+            .await
+            .map(|payload| payload.extra)
+        };
+        // pass authentication, era and nonce come before the fee payment
+        let fee = |extra: Vec<u8>| extra[3..].to_vec();
+
+        let body = tx_data(Some(0), &remark);
+        assert_eq!(fee(extra(body).await.unwrap()), [0x00, 0x00]);
+
+        let body = ExtrinsicBody {
+            tip: Some(1_000),
+            ..tx_data(Some(0), &remark)
+        };
+        assert_eq!(
+            fee(extra(body).await.unwrap()),
+            [&Compact(1_000u128).encode()[..], &[0x00]].concat()
+        );
+
+        let body = ExtrinsicBody {
+            tip: Some(1),
+            fee_asset: Some(json!({ "Sibling": { "id": 1000, "pallet": 50, "index": 1984 } })),
+            ..tx_data(Some(0), &remark)
+        };
+        assert_eq!(
+            fee(extra(body).await.unwrap()),
+            [
+                &[0x04, 0x01, 0x01][..],
+                &1000u16.encode(),
+                &[50],
+                &1984u32.encode()
+            ]
+            .concat()
+        );
+
+        let body = ExtrinsicBody {
+            fee_asset: Some(json!({ "Unknown": 1 })),
+            ..tx_data(Some(0), &remark)
+        };
+        assert!(matches!(extra(body).await, Err(Error::Encode(_))));
+    }
+
+    #[async_std::test]
+    async fn estimate_fees_before_signing() {
+        let mut meta = kreivo();
+        let remark = json!({ "remark": ["1"] });
+        let query_info = [
+            Compact(2_000u64).encode(),
+            Compact(128u64).encode(),
+            vec![0],
+            1_500u128.encode(),
+        ]
+        .concat();
+        let fee_details = [
+            vec![1],
+            1_000u128.encode(),
+            300u128.encode(),
+            200u128.encode(),
+            5u128.encode(),
+        ]
+        .concat();
+        let fee = |meta: Metadata| {
+            let chain = MockBackend::new(meta.clone())
+                .with_runtime_call("TransactionPaymentApi_query_info", query_info.clone())
+                .with_runtime_call(
+                    "TransactionPaymentApi_query_fee_details",
+                    fee_details.clone(),
+                );
+            let tx_data = ExtrinsicBody {
+                tip: Some(5),
+                ..tx_data(Some(0), &remark)
+            };
+            async move {
+                let signer = SignerFn::try_from((ALICE, |_: &[u8]| async { Ok([7; 64]) })).unwrap();
+                let extensions = Extensions::default();
+                match estimate_fee(chain, &meta, "system/remark", tx_data, signer, &extensions)
+                    .await?
+                {
+                    Response::Fee(fee) => Ok::<_, Error>(serde_json::to_value(fee).unwrap()),
+                    _ => unreachable!("fee estimations respond with the fee"),
+                }
+            }
+        };
+        let expected = json!({
+            "weight": { "ref_time": 2000, "proof_size": 128 },
+            "class": "normal",
+            "partial_fee": 1500,
+            "details": {
+                "inclusion_fee": { "base_fee": 1000, "len_fee": 300, "adjusted_weight_fee": 200 },
+                "tip": 5,
+            },
+        });
+        assert_eq!(fee(meta.clone()).await.unwrap(), expected);
+
+        // without runtime API metadata the usual types are assumed
+        #[cfg(feature = "v15")]
+        if let Metadata::V15(m) = &mut meta {
+            m.apis.clear();
+        }
+        assert_eq!(fee(meta.clone()).await.unwrap(), expected);
+
+        let chain = MockBackend::new(meta.clone());
+        let signer = SignerFn::try_from((ALICE, |_: &[u8]| async { Ok([7; 64]) })).unwrap();
+        let res = estimate_fee(
+            chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(0), &remark),
+            signer,
+            &Extensions::default(),
+        )
+        .await;
+        assert!(res.is_err(), "the runtime API is needed");
+    }
+
+    #[async_std::test]
+    async fn nonce_of_the_account() {
+        let meta = kreivo();
+        let remark = json!({ "remark": ["1"] });
+        let account = format!("system/account/0x{}", hex::encode(ALICE));
+        let account_info = json!({
+            "nonce": 7, "consumers": 0, "providers": 1, "sufficients": 0,
+            "data": { "free": 10, "reserved": 0, "frozen": 0, "flags": 0 }
+        });
+        let mut chain = MockBackend::new(meta.clone());
+        chain.insert(&account, account_info.clone()).unwrap();
+        let nonce = |chain| {
+            let tx_data = tx_data(None, &remark);
+            let meta = &meta;
+            async move {
+                let payload = signing_payload(
+                    &chain,
+                    meta,
+                    "system/remark",
+                    tx_data,
+                    &ALICE,
+                    Scheme::Sr25519,
+                    &Extensions::default(),
+                )
+                .await?;
+                let decoded = extrinsic::decode(meta, &payload.attach_signature([0; 64]))?;
+                Ok::<_, Error>(decoded.extensions["CheckNonce"].clone())
+            }
+        };
+        assert_eq!(nonce(chain.clone()).await.unwrap(), json!(7));
+
+        // an account info that isn't of the expected type is an error
+        let key = StorageKey::build_with_registry(
+            meta.registry(),
+            meta.pallet_by_name("System").unwrap(),
+            "Account",
+            &[format!("0x{}", hex::encode(ALICE))],
+        )
+        .unwrap();
+        let mut value =
+            scales::to_vec_with_info(&account_info, (meta.registry(), key.ty).into()).unwrap();
+        value.push(0);
+        chain
+            .insert(
+                &format!("0x{}", hex::encode(key.key())),
+                json!(hex::encode(value)),
+            )
+            .unwrap();
+        assert!(matches!(nonce(chain.clone()).await, Err(Error::Decode(_))));
+
+        chain.insert(&account, JsonValue::Null).unwrap();
+        assert_eq!(nonce(chain).await.unwrap(), json!(0));
+    }
+
+    #[async_std::test]
+    async fn decode_built_extrinsics() {
+        let meta = kreivo();
+        let chain = MockBackend::new(meta.clone());
+        let remark = json!({ "remark": ["1", "2", "3"] });
+        let extensions = Extensions::default();
+
+        let payload = signing_payload(
+            &chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(3), &remark),
+            &ALICE,
+            Scheme::Ed25519,
+            &extensions,
+        )
+        .await
+        .unwrap();
+        let signed = payload.attach_signature([7; 64]);
+        let bare = unsigned_extrinsic(
+            &chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(3), &remark),
+            ExtrinsicMode::Unsigned,
+            &extensions,
+        )
+        .await
+        .unwrap();
+
+        let decoded = extrinsic::decode(&meta, &signed).unwrap();
+        assert_eq!(decoded.version, 4);
+        assert_eq!(decoded.mode, ExtrinsicMode::Signed);
+        assert_eq!(decoded.address, Some(json!({ "Id": ALICE })));
+        assert_eq!(decoded.signature, Some(json!({ "Ed25519": vec![7; 64] })));
+        assert_eq!(decoded.extensions["CheckNonce"], json!(3));
+        assert_eq!(
+            serde_json::to_value(&decoded.call).unwrap(),
+            json!({ "pallet": "System", "call": "remark", "args": { "remark": [1, 2, 3] } })
+        );
+        assert!(extrinsic::decode(&meta, &signed[..signed.len() - 1]).is_err());
+
+        // submitted extrinsics land in the latest block of the mock
+        chain.submit(&signed).await.unwrap();
+        chain.submit(&bare).await.unwrap();
+        let Response::Extrinsics(block) = block(&chain, &meta, Some(0)).await.unwrap() else {
+            panic!("expected the extrinsics of the block");
+        };
+        assert_eq!(block.len(), 2);
+        assert_eq!(block[1].mode, ExtrinsicMode::Unsigned);
+        assert!(block[1].address.is_none() && block[1].extensions.is_empty());
+        assert_eq!(block[1].call.call, "remark");
+    }
+
+    #[async_std::test]
+    async fn batch_calls_in_one_extrinsic() {
+        let meta = kreivo();
+        let signer = || SignerFn::try_from((ALICE, |_: &[u8]| async { Ok([7; 64]) })).unwrap();
+        let builder = || {
+            SubeBuilder::default()
+                .with_url("kreivo.io")
+                .with_meta(meta.clone())
+                .with_call("system/remark", json!({ "remark": ["1", "2"] }))
+                .with_call("system/remark_with_event", json!({ "remark": ["3"] }))
+                .with_nonce(0)
+                .with_genesis_hash([1; 32])
+        };
+
+        let extrinsic = builder().with_signer(signer()).sign().await.unwrap();
+        let decoded = extrinsic::decode(&meta, &extrinsic).unwrap();
+        assert_eq!(decoded.address, Some(json!({ "Id": ALICE })));
+        assert_eq!(
+            serde_json::to_value(&decoded.call).unwrap(),
+            json!({
+                "pallet": "Utility",
+                "call": "batch_all",
+                "args": { "calls": [
+                    { "System": { "remark": { "remark": [1, 2] } } },
+                    { "System": { "remark_with_event": { "remark": [3] } } },
+                ]}
+            })
+        );
+
+        let extrinsic = builder()
+            .with_batch_mode(BatchMode::ForceBatch)
+            .with_signer(signer())
+            .sign()
+            .await
+            .unwrap();
+        let decoded = extrinsic::decode(&meta, &extrinsic).unwrap();
+        assert_eq!(decoded.call.call, "force_batch");
+
+        // calls are encoded one by one, any invalid call fails the batch
+        let res = builder()
+            .with_call("system/unknown", json!({}))
+            .with_signer(signer())
+            .sign()
+            .await;
+        assert!(matches!(res, Err(Error::Encode(_))));
+    }
+
+    #[async_std::test]
+    async fn authenticate_with_a_pass() {
+        const DEVICE: [u8; 32] = [3; 32];
+        const AUTHORITY: [u8; 32] = [4; 32];
+        const USER: [u8; 32] = [5; 32];
+        let meta = kreivo();
+        let signer = || SignerFn::try_from((ALICE, |_: &[u8]| async { Ok([7; 64]) })).unwrap();
+        let builder = || {
+            SubeBuilder::default()
+                .with_url("kreivo.io/system/remark")
+                .with_meta(meta.clone())
+                .with_body(json!({ "remark": ["1"] }))
+                .with_nonce(0)
+                .with_genesis_hash([1; 32])
+                .with_signer(signer())
+        };
+        let params = json!({
+            "device_id": DEVICE,
+            "credential": { "WebAuthn": {
+                "meta": { "authority_id": AUTHORITY, "user_id": USER, "context": 0 },
+                "authenticator_data": [1, 2],
+                "client_data": [3],
+                "signature": [4, 5, 6],
+            }},
+        });
+
+        let extrinsic = builder().as_pass(params.clone()).sign().await.unwrap();
+        let decoded = extrinsic::decode(&meta, &extrinsic).unwrap();
+        assert_eq!(decoded.extensions["PassAuthenticate"], params);
+        assert_eq!(decoded.call.call, "remark");
+        let mut authentication = [&[0x01][..], &DEVICE, &[0x00], &AUTHORITY, &USER].concat();
+        authentication.extend([0, 0, 0, 0, 8, 1, 2, 4, 3, 12, 4, 5, 6]);
+        assert!(extrinsic
+            .windows(authentication.len())
+            .any(|w| w == authentication));
+
+        // without a pass the extrinsic is only signed
+        let extrinsic = builder().sign().await.unwrap();
+        let decoded = extrinsic::decode(&meta, &extrinsic).unwrap();
+        assert_eq!(decoded.extensions["PassAuthenticate"], JsonValue::Null);
+
+        // kreivo only authenticates passes with WebAuthn credentials
+        let params = json!({ "device_id": DEVICE, "credential": { "Password": [1] } });
+        let res = builder().as_pass(params).sign().await;
+        assert!(matches!(res, Err(Error::Encode(_))));
+    }
+
+    #[async_std::test]
+    async fn wrap_calls_for_proxies_and_multisigs() {
+        const BOB: [u8; 32] = [2; 32];
+        const CHARLIE: [u8; 32] = [3; 32];
+        let meta = kreivo();
+        let call_info = [
+            Compact(1_000u64).encode(),
+            Compact(64u64).encode(),
+            vec![0],
+            0u128.encode(),
+        ]
+        .concat();
+        let mut chain = MockBackend::new(meta.clone())
+            .with_runtime_call("TransactionPaymentCallApi_query_call_info", call_info);
+
+        // the call of the extrinsic alice signs
+        async fn call_of<C: Call + fmt::Debug>(
+            chain: &MockBackend,
+            meta: &Metadata,
+            body: C,
+        ) -> Result<JsonValue> {
+            let extensions = Extensions::default();
+            let payload = signing_payload(
+                chain,
+                meta,
+                "system/remark",
+                tx_data(Some(0), body),
+                &ALICE,
+                Scheme::Sr25519,
+                &extensions,
+            )
+            .await?;
+            let call = extrinsic::decode_call(meta, &payload.call)?;
+            Ok(serde_json::to_value(call).unwrap())
+        }
+        let remark = json!({ "remark": ["1", "2"] });
+        let inner = json!({ "System": { "remark": { "remark": [1, 2] } } });
+
+        let proxy = Wrapper::Proxy {
+            real: BOB.to_vec(),
+            force_proxy_type: Some("Any".into()),
+        };
+        let call = call_of(&chain, &meta, Wrapped::new(proxy, remark.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            call,
+            json!({
+                "pallet": "Proxy",
+                "call": "proxy",
+                "args": { "real": { "Id": BOB }, "force_proxy_type": "Any", "call": inner }
+            })
+        );
+
+        // the sender is left out of the other signatories which are sorted
+        let multisig = Wrapper::Multisig {
+            threshold: 2,
+            other_signatories: vec![CHARLIE.to_vec(), ALICE.to_vec(), BOB.to_vec()],
+        };
+        let call = call_of(
+            &chain,
+            &meta,
+            Wrapped::new(multisig.clone(), remark.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            call,
+            json!({
+                "pallet": "Multisig",
+                "call": "as_multi",
+                "args": {
+                    "threshold": 2,
+                    "other_signatories": [BOB, CHARLIE],
+                    "maybe_timepoint": null,
+                    "call": inner,
+                    "max_weight": { "ref_time": 1000, "proof_size": 64 },
+                }
+            })
+        );
+
+        // later approvals refer to the timepoint of the first one
+        let account = call::multisig_account(&ALICE, &[BOB.to_vec(), CHARLIE.to_vec()], 2);
+        let call_hash = hash(
+            &meta::Hasher::Blake2_256,
+            encode_call(&meta, "system/remark", &remark).unwrap(),
+        );
+        let bytes = |account: &[u8]| account.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+        chain
+            .insert(
+                &format!(
+                    "multisig/multisigs/0x{}/0x{}",
+                    hex::encode(&account),
+                    hex::encode(call_hash)
+                ),
+                json!({
+                    "when": { "height": "5", "index": "1" },
+                    "deposit": "0",
+                    "depositor": bytes(&BOB),
+                    "approvals": [bytes(&BOB)],
+                }),
+            )
+            .unwrap();
+        let call = call_of(
+            &chain,
+            &meta,
+            Wrapped::new(multisig.clone(), remark.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            call["args"]["maybe_timepoint"],
+            json!({ "height": 5, "index": 1 })
+        );
+
+        // wrappers can be nested and wrap any call like a batch
+        let batch = Batch::default().with_call("system/remark", remark.clone());
+        let proxy = Wrapper::Proxy {
+            real: BOB.to_vec(),
+            force_proxy_type: None,
+        };
+        let call = call_of(&chain, &meta, Wrapped::new(proxy, batch))
+            .await
+            .unwrap();
+        assert_eq!(call["args"]["force_proxy_type"], JsonValue::Null);
+        assert_eq!(
+            call["args"]["call"]["Utility"]["batch_all"]["calls"],
+            json!([inner])
+        );
+
+        let derivative = Wrapper::Dispatch {
+            path: "utility/as_derivative".into(),
+            args: json!({ "index": "1" }),
+        };
+        let call = call_of(&chain, &meta, Wrapped::new(derivative, remark.clone()))
+            .await
+            .unwrap();
+        assert_eq!(call["args"], json!({ "index": 1, "call": inner }));
+
+        // a multisig approval needs a sender
+        let res = unsigned_extrinsic(
+            &chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(0), Wrapped::new(multisig, remark)),
+            ExtrinsicMode::Unsigned,
+            &Extensions::default(),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::BadInput)));
+    }
+
+    #[async_std::test]
+    async fn unsigned_and_general_extrinsics() {
+        let meta = kreivo();
+        let remark = &json!({ "remark": ["1", "2"] });
+        let build = |meta: Metadata, mode| async move {
+            let chain = Offline(meta);
+            let extensions = Extensions::default();
+            unsigned_extrinsic(
+                &chain,
+                &chain.0,
+                "system/remark",
+                tx_data(Some(0), remark),
+                mode,
+                &extensions,
+            )
+            .await
+        };
+        let call = encode_call(&meta, "system/remark", remark).unwrap();
+
+        let bare = build(meta.clone(), ExtrinsicMode::Unsigned).await.unwrap();
+        assert_eq!(bare, length_prefixed([&[0x04][..], &call].concat()));
+        assert!(matches!(
+            build(meta.clone(), ExtrinsicMode::General).await,
+            Err(Error::UnsupportedExtrinsic(ExtrinsicMode::General))
+        ));
+
+        // the metadata of current runtimes only declares version 4
+        let header = |mode| extrinsic::header(&meta, mode);
+        assert_eq!(header(ExtrinsicMode::Signed).unwrap(), 0x84);
+        assert_eq!(header(ExtrinsicMode::Unsigned).unwrap(), 0x04);
+        let err = header(ExtrinsicMode::General).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "General extrinsics are not supported by the extrinsic versions of the metadata"
+        );
+    }
+
+    #[async_std::test]
+    async fn extrinsics_of_version_5_runtimes() {
+        let meta = kreivo_v5();
+        let chain = Offline(meta.clone());
+        let remark = &json!({ "remark": ["1", "2"] });
+        let extensions = Extensions::default();
+        let build = |mode| {
+            let tx_data = tx_data(Some(0), remark);
+            unsigned_extrinsic(&chain, &meta, "system/remark", tx_data, mode, &extensions)
+        };
+        let call = encode_call(&meta, "system/remark", remark).unwrap();
+        let (extra, _) =
+            encode_extensions(&chain, &meta, &tx_data(Some(0), remark), None, &extensions)
+                .await
+                .unwrap();
+
+        // header, version of the transaction extensions, their values and the call
+        let general = build(ExtrinsicMode::General).await.unwrap();
+        let inner = [&[0x45, 0x00][..], &extra, &call].concat();
+        assert_eq!(general, length_prefixed(inner));
+        let decoded = extrinsic::decode(&meta, &general).unwrap();
+        assert_eq!((decoded.version, decoded.mode), (5, ExtrinsicMode::General));
+        assert_eq!(decoded.extensions["CheckNonce"], json!(0));
+        assert_eq!(
+            serde_json::to_value(&decoded.call).unwrap(),
+            json!({ "pallet": "System", "call": "remark", "args": { "remark": [1, 2] } })
+        );
+
+        let bare = build(ExtrinsicMode::Unsigned).await.unwrap();
+        assert_eq!(bare, length_prefixed([&[0x05][..], &call].concat()));
+
+        // signed extrinsics only exist in version 4
+        let payload = signing_payload(
+            &chain,
+            &meta,
+            "system/remark",
+            tx_data(Some(0), remark),
+            &ALICE,
+            Scheme::Sr25519,
+            &extensions,
+        )
+        .await;
+        assert!(matches!(
+            payload,
+            Err(Error::UnsupportedExtrinsic(ExtrinsicMode::Signed))
+        ));
+    }
+
+    #[test]
+    fn address_and_signature_from_metadata() {
+        let meta = kreivo();
+
+        // MultiAddress::Id and MultiAddress::Address20
+        assert_eq!(
+            extrinsic::encode_address(&meta, &ALICE).unwrap(),
+            [&[0][..], &ALICE].concat()
+        );
+        assert_eq!(
+            extrinsic::encode_address(&meta, &[2; 20]).unwrap(),
+            [&[4][..], &[2; 20]].concat()
+        );
+        assert!(extrinsic::encode_address(&meta, &[2; 16]).is_err());
+
+        let variant = |scheme| extrinsic::signature_variant(&meta, scheme).unwrap();
+        assert_eq!(variant(Scheme::Ed25519), Some(0));
+        assert_eq!(variant(Scheme::Sr25519), Some(1));
+        assert_eq!(variant(Scheme::Ecdsa), Some(2));
+    }
+
+    #[test]
+    fn decode_double_map_keys() {
+        let meta = kreivo();
+        // Multisig.Multisigs: Twox64Concat(AccountId32), Blake2_128Concat([u8; 32])
+        let raw_key = hex!(
+            "7474449cca95dc5d0c00e71735a6d17d3cd15a3fd6e04e47bee3922dbfa92c8d"
+            "518366b5b1bc7c99d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+            "c035f853fcd0f0589e30c9e2dc1a0f570101010101010101010101010101010101010101010101010101010101010101"
+        );
+
+        assert_eq!(
+            entry_keys(&meta, "Multisig", "Multisigs", &raw_key),
+            json!([ALICE, vec![1; 32]])
+        );
+    }
+
+    #[test]
+    fn decode_nmap_keys() {
+        let meta = kreivo();
+        // CommunityMemberships.Account: Blake2_128Concat(AccountId32, u16, u32)
+        let raw_key = hex!(
+            "dab401be300e2c5856a4ed696093a6f3b99d880ec681799c0cf30e8886371da9"
+            "de1e86a9a8c739864cf3cc5ec2bea59fd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+            "ead9a09ae23f059006f2bea50e245ff40100"
+            "9017d1c733ec2aeaa6fd0230614e397e2a000000"
+        );
+
+        assert_eq!(
+            entry_keys(&meta, "CommunityMemberships", "Account", &raw_key),
+            json!([ALICE, 1, 42])
+        );
+
+        // keys given in the query are decoded back from the raw key as well
+        let pallet = meta.pallet_by_name("CommunityMemberships").unwrap();
+        let key = StorageKey::build_with_registry(
+            meta.registry(),
+            pallet,
+            "Account",
+            &[format!("0x{}", hex::encode(ALICE))],
+        )
+        .unwrap();
+        assert!(key.is_partial());
+        assert!(raw_key.starts_with(&key.key()));
+        let (keys, _) = decode_entry(meta.registry(), &key, &raw_key, None).unwrap();
+        assert_eq!(serde_json::to_value(keys).unwrap(), json!([ALICE, 1, 42]));
+    }
+
+    #[test]
+    fn decode_identity_key() {
+        let meta = kreivo();
+        // Preimage.StatusFor: Identity(H256)
+        let raw_key = hex!(
+            "d8f314b7f4e6b095f0f8ee4656a4482555b1ae8eced5522f3c4049bc84eda4a8"
+            "0101010101010101010101010101010101010101010101010101010101010101"
+        );
+
+        assert_eq!(
+            entry_keys(&meta, "Preimage", "StatusFor", &raw_key),
+            json!([vec![1; 32]])
+        );
+    }
+
+    #[test]
+    fn opaque_keys_are_left_hashed() {
+        let meta = kusama();
+        // CoretimeAssignmentProvider.CoreDescriptors: Twox256(CoreIndex)
+        let pallet = meta.pallet_by_name("CoretimeAssignmentProvider").unwrap();
+        let key = StorageKey::build_with_registry(
+            meta.registry(),
+            pallet,
+            "CoreDescriptors",
+            &[] as &[&str],
+        )
+        .unwrap();
+        let raw_key = [key.key(), [0xab; 32].to_vec()].concat();
+
+        assert_eq!(
+            entry_keys(
+                &meta,
+                "CoretimeAssignmentProvider",
+                "CoreDescriptors",
+                &raw_key
+            ),
+            json!([format!("0x{}", hex::encode([0xab; 32]))])
+        );
+    }
+
+    #[test]
+    fn truncated_keys_fail_to_decode() {
+        let meta = kreivo();
+        let pallet = meta.pallet_by_name("Multisig").unwrap();
+        let key =
+            StorageKey::build_with_registry(meta.registry(), pallet, "Multisigs", &[] as &[&str])
+                .unwrap();
+        let raw_key = [key.key(), vec![0; 4]].concat();
+
+        assert!(decode_entry(meta.registry(), &key, &raw_key, None).is_err());
+
+        // keys cut off right after their Blake2_128Concat hash
+        for (pallet, item) in [("System", "Account"), ("Assets", "Asset")] {
+            let pallet = meta.pallet_by_name(pallet).unwrap();
+            let key =
+                StorageKey::build_with_registry(meta.registry(), pallet, item, &[] as &[&str])
+                    .unwrap();
+            let raw_key = [key.key(), vec![0; 16]].concat();
+            assert!(matches!(
+                decode_entry(meta.registry(), &key, &raw_key, None),
+                Err(Error::BadKey)
+            ));
+        }
+    }
+}
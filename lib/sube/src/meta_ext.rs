@@ -1,38 +1,139 @@
 use crate::prelude::*;
 use core::borrow::Borrow;
 
-use codec::Decode;
-use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use codec::{Decode, Encode};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use scale_info::form::PortableForm;
 use scales::to_bytes_with_info;
+use serde::Serialize;
 
-#[cfg(feature = "v14")]
-pub use v14::*;
+pub use frame_metadata::v14::{
+    PalletCallMetadata, PalletConstantMetadata, PalletErrorMetadata, PalletStorageMetadata,
+    StorageEntryType, StorageHasher,
+};
+pub use scale_info::PortableRegistry;
 
 use crate::hasher::hash;
 type TypeId = u32;
 
-mod v14 {
+pub type EntryType = StorageEntryType<PortableForm>;
+pub type Hasher = StorageHasher;
+pub type Type = scale_info::Type<PortableForm>;
+
+#[cfg(feature = "v14")]
+pub mod v14 {
     use frame_metadata::v14::*;
     use scale_info::form::PortableForm;
     pub type Metadata = RuntimeMetadataV14;
     pub type PalletMeta = PalletMetadata<PortableForm>;
-    pub type EntryType = StorageEntryType<PortableForm>;
-    pub type Hasher = StorageHasher;
-    pub use scale_info::PortableRegistry;
-    pub type Type = scale_info::Type<PortableForm>;
 }
 
-// Decode metadata from its raw prefixed format to the currently
-// active metadata version.
+#[cfg(feature = "v15")]
+pub mod v15 {
+    use frame_metadata::v15::*;
+    use scale_info::form::PortableForm;
+    pub type Metadata = RuntimeMetadataV15;
+    pub type PalletMeta = PalletMetadata<PortableForm>;
+}
+
+/// Runtime metadata in any of the enabled versions, the concrete version
+/// is picked at runtime from the prefix of the encoded metadata.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum Metadata {
+    #[cfg(feature = "v14")]
+    V14(v14::Metadata),
+    #[cfg(feature = "v15")]
+    V15(v15::Metadata),
+}
+
+impl Metadata {
+    pub fn version(&self) -> u32 {
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(_) => 14,
+            #[cfg(feature = "v15")]
+            Self::V15(_) => 15,
+        }
+    }
+}
+
+// Values decoded with the types of the metadata can keep it alive
+impl AsRef<PortableRegistry> for Metadata {
+    fn as_ref(&self) -> &PortableRegistry {
+        self.registry()
+    }
+}
+
+// Metadata is encoded in its prefixed form so it can be decoded back
+// without knowing in advance the version it was encoded with.
+impl Encode for Metadata {
+    fn encode_to<T: codec::Output + ?Sized>(&self, dest: &mut T) {
+        META_RESERVED.encode_to(dest);
+        // the index of the `RuntimeMetadata` variant matches its version
+        (self.version() as u8).encode_to(dest);
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => m.encode_to(dest),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => m.encode_to(dest),
+        }
+    }
+}
+
+impl Decode for Metadata {
+    fn decode<I: codec::Input>(input: &mut I) -> core::result::Result<Self, codec::Error> {
+        let meta: RuntimeMetadataPrefixed = Decode::decode(input)?;
+        meta.try_into()
+    }
+}
+
+impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
+    type Error = codec::Error;
+
+    fn try_from(meta: RuntimeMetadataPrefixed) -> core::result::Result<Self, Self::Error> {
+        match meta.1 {
+            #[cfg(feature = "v14")]
+            RuntimeMetadata::V14(m) => Ok(Metadata::V14(m)),
+            #[cfg(feature = "v15")]
+            RuntimeMetadata::V15(m) => Ok(Metadata::V15(m)),
+            _ => Err("Metadata version not supported".into()),
+        }
+    }
+}
+
+// Decode metadata from its raw prefixed format to the matching
+// metadata version if it's enabled.
 pub fn from_bytes(bytes: &mut &[u8]) -> core::result::Result<Metadata, codec::Error> {
-    let meta: RuntimeMetadataPrefixed = Decode::decode(bytes)?;
-    let meta = match meta.1 {
-        RuntimeMetadata::V14(m) => m,
-        _ => unreachable!("Metadata version not supported"),
-    };
-    Ok(meta)
+    Metadata::decode(bytes)
+}
+
+/// Identifier and type information of a signed extension declared
+/// by the runtime for its extrinsics.
+#[derive(Clone, Debug)]
+pub struct SignedExtensionMeta<'a> {
+    pub identifier: &'a str,
+    pub ty: TypeId,
+    pub additional_signed: TypeId,
 }
 
+/// Types the runtime uses for the parts of its extrinsics
+#[derive(Clone, Debug)]
+pub struct ExtrinsicTypes {
+    pub address: TypeId,
+    pub call: TypeId,
+    pub signature: TypeId,
+    pub extra: TypeId,
+}
+
+/// Types of the parameters and of the output of a runtime API method
+#[derive(Clone, Debug)]
+pub struct RuntimeApiMethodMeta {
+    pub inputs: Vec<TypeId>,
+    pub output: TypeId,
+}
+
+#[derive(Clone, Debug)]
 pub struct BlockInfo {
     pub number: u64,
     pub hash: [u8; 32],
@@ -47,35 +148,231 @@ impl From<BlockInfo> for Vec<u8> {
 /// An extension trait for a decoded metadata object that provides
 /// convenient methods to navigate and extract data from it.
 pub trait Meta {
-    type Pallet: Pallet;
+    type Pallet: Pallet + ?Sized;
 
     fn pallets(&self) -> impl Iterator<Item = &Self::Pallet>;
 
+    fn registry(&self) -> &PortableRegistry;
+
+    fn signed_extensions(&self) -> impl Iterator<Item = SignedExtensionMeta<'_>>;
+
+    fn extrinsic_types(&self) -> Option<ExtrinsicTypes>;
+
+    /// Versions of the extrinsic format the runtime accepts
+    fn extrinsic_versions(&self) -> Vec<u8>;
+
     fn pallet_by_name(&self, name: &str) -> Option<&Self::Pallet> {
         self.pallets()
             .find(|p| p.name().to_lowercase() == name.to_lowercase())
     }
+
+    /// Runtime APIs are only described by metadata since V15
+    fn runtime_api(&self, _api: &str, _method: &str) -> Option<RuntimeApiMethodMeta> {
+        None
+    }
 }
-impl Meta for Metadata {
-    type Pallet = PalletMeta;
+
+#[cfg(feature = "v14")]
+impl Meta for v14::Metadata {
+    type Pallet = v14::PalletMeta;
+
+    fn pallets(&self) -> impl Iterator<Item = &Self::Pallet> {
+        self.pallets.iter()
+    }
+
+    fn registry(&self) -> &PortableRegistry {
+        &self.types
+    }
+
+    fn signed_extensions(&self) -> impl Iterator<Item = SignedExtensionMeta<'_>> {
+        self.extrinsic
+            .signed_extensions
+            .iter()
+            .map(|e| SignedExtensionMeta {
+                identifier: &e.identifier,
+                ty: e.ty.id,
+                additional_signed: e.additional_signed.id,
+            })
+    }
+
+    fn extrinsic_versions(&self) -> Vec<u8> {
+        vec![self.extrinsic.version]
+    }
+
+    /// V14 only references the `UncheckedExtrinsic` type, the types of
+    /// its parts are found in its type parameters
+    fn extrinsic_types(&self) -> Option<ExtrinsicTypes> {
+        let ty = self.types.resolve(self.extrinsic.ty.id)?;
+        let param = |name: &str| {
+            ty.type_params
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| p.ty)
+                .map(|t| t.id)
+        };
+        Some(ExtrinsicTypes {
+            address: param("Address")?,
+            call: param("Call")?,
+            signature: param("Signature")?,
+            extra: param("Extra")?,
+        })
+    }
+}
+
+#[cfg(feature = "v15")]
+impl Meta for v15::Metadata {
+    type Pallet = v15::PalletMeta;
 
     fn pallets(&self) -> impl Iterator<Item = &Self::Pallet> {
         self.pallets.iter()
     }
+
+    fn registry(&self) -> &PortableRegistry {
+        &self.types
+    }
+
+    fn signed_extensions(&self) -> impl Iterator<Item = SignedExtensionMeta<'_>> {
+        self.extrinsic
+            .signed_extensions
+            .iter()
+            .map(|e| SignedExtensionMeta {
+                identifier: &e.identifier,
+                ty: e.ty.id,
+                additional_signed: e.additional_signed.id,
+            })
+    }
+
+    fn extrinsic_versions(&self) -> Vec<u8> {
+        vec![self.extrinsic.version]
+    }
+
+    fn extrinsic_types(&self) -> Option<ExtrinsicTypes> {
+        Some(ExtrinsicTypes {
+            address: self.extrinsic.address_ty.id,
+            call: self.extrinsic.call_ty.id,
+            signature: self.extrinsic.signature_ty.id,
+            extra: self.extrinsic.extra_ty.id,
+        })
+    }
+
+    fn runtime_api(&self, api: &str, method: &str) -> Option<RuntimeApiMethodMeta> {
+        let method = self
+            .apis
+            .iter()
+            .find(|a| a.name == api)?
+            .methods
+            .iter()
+            .find(|m| m.name == method)?;
+
+        Some(RuntimeApiMethodMeta {
+            inputs: method.inputs.iter().map(|i| i.ty.id).collect(),
+            output: method.output.id,
+        })
+    }
+}
+
+impl Meta for Metadata {
+    type Pallet = dyn Pallet;
+
+    fn pallets(&self) -> impl Iterator<Item = &Self::Pallet> {
+        let pallets: Box<dyn Iterator<Item = &Self::Pallet> + '_> = match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => Box::new(m.pallets().map(|p| p as &dyn Pallet)),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => Box::new(m.pallets().map(|p| p as &dyn Pallet)),
+        };
+        pallets
+    }
+
+    fn registry(&self) -> &PortableRegistry {
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => m.registry(),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => m.registry(),
+        }
+    }
+
+    fn signed_extensions(&self) -> impl Iterator<Item = SignedExtensionMeta<'_>> {
+        let extensions: Box<dyn Iterator<Item = SignedExtensionMeta<'_>> + '_> = match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => Box::new(m.signed_extensions()),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => Box::new(m.signed_extensions()),
+        };
+        extensions
+    }
+
+    fn extrinsic_types(&self) -> Option<ExtrinsicTypes> {
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => m.extrinsic_types(),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => m.extrinsic_types(),
+        }
+    }
+
+    fn extrinsic_versions(&self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => m.extrinsic_versions(),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => m.extrinsic_versions(),
+        }
+    }
+
+    fn runtime_api(&self, api: &str, method: &str) -> Option<RuntimeApiMethodMeta> {
+        match self {
+            #[cfg(feature = "v14")]
+            Self::V14(m) => m.runtime_api(api, method),
+            #[cfg(feature = "v15")]
+            Self::V15(m) => m.runtime_api(api, method),
+        }
+    }
 }
 
 pub trait Pallet {
     fn name(&self) -> &str;
+    fn index(&self) -> u8;
+    fn calls(&self) -> Option<&PalletCallMetadata<PortableForm>>;
+    fn storage(&self) -> Option<&PalletStorageMetadata<PortableForm>>;
+    fn constants(&self) -> &[PalletConstantMetadata<PortableForm>];
+    fn error(&self) -> Option<&PalletErrorMetadata<PortableForm>>;
 }
-impl Pallet for PalletMeta {
-    fn name(&self) -> &str {
-        &self.name
-    }
+
+macro_rules! impl_pallet {
+    ($pallet:ty) => {
+        impl Pallet for $pallet {
+            fn name(&self) -> &str {
+                &self.name
+            }
+            fn index(&self) -> u8 {
+                self.index
+            }
+            fn calls(&self) -> Option<&PalletCallMetadata<PortableForm>> {
+                self.calls.as_ref()
+            }
+            fn storage(&self) -> Option<&PalletStorageMetadata<PortableForm>> {
+                self.storage.as_ref()
+            }
+            fn constants(&self) -> &[PalletConstantMetadata<PortableForm>] {
+                &self.constants
+            }
+            fn error(&self) -> Option<&PalletErrorMetadata<PortableForm>> {
+                self.error.as_ref()
+            }
+        }
+    };
 }
+#[cfg(feature = "v14")]
+impl_pallet!(v14::PalletMeta);
+#[cfg(feature = "v15")]
+impl_pallet!(v15::PalletMeta);
 
 #[derive(Clone, Debug)]
 pub enum KeyValue {
-    Empty(TypeId),
+    // type id, hasher
+    Empty(TypeId, Hasher),
     // type id, hash, encoded_value
     Value((TypeId, Vec<u8>, Vec<u8>, Hasher)),
 }
@@ -104,7 +401,7 @@ impl StorageKey {
             .args
             .iter()
             .map(|e| match e {
-                KeyValue::Empty(_) => &[],
+                KeyValue::Empty(..) => &[],
                 KeyValue::Value((_, hash, _, _)) => &hash[..],
             })
             .collect::<Vec<&[u8]>>()
@@ -119,13 +416,12 @@ impl StorageKey {
 
     pub fn build_with_registry<T: AsRef<str>>(
         registry: &PortableRegistry,
-        meta: &PalletMeta,
+        pallet: &(impl Pallet + ?Sized),
         item: &str,
         map_keys: &[T],
     ) -> crate::Result<Self> {
-        let entry = meta
-            .storage
-            .as_ref()
+        let entry = pallet
+            .storage()
             .and_then(|s| s.entries.iter().find(|e| e.name == item))
             .ok_or(crate::Error::CantFindMethodInPallet)?;
         log::trace!(
@@ -136,7 +432,7 @@ impl StorageKey {
                 .collect::<Vec<&str>>()
                 .join(", ")
         );
-        entry.ty.key(registry, &meta.name, &entry.name, map_keys)
+        entry.ty.key(registry, pallet.name(), &entry.name, map_keys)
     }
 }
 
@@ -201,15 +497,14 @@ pub trait EntryTy {
                     .map(|(i, type_id)| {
                         log::trace!("type_call_ids.i={} type_call_ids.type_id={}", i, type_id);
                         let k = map_keys.get(i);
-                        let hasher = hashers.get(i).expect("hasher not found");
+                        let hasher = hashers.get(i).expect("hasher not found").borrow();
 
                         if k.is_none() {
-                            return KeyValue::Empty(type_id);
+                            return KeyValue::Empty(type_id, hasher.clone());
                         }
 
                         let k = k.expect("it must exist").as_ref();
 
-                        let hasher = hasher.borrow();
                         let mut out = vec![];
 
                         if let Some(k) = k.strip_prefix("0x") {
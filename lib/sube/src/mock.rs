@@ -0,0 +1,633 @@
+/*!
+Backends to test code that uses sube without a node at hand.
+
+[`MockBackend`] answers from an in-memory storage seeded from JSON, keeps the
+extrinsics it's sent and serves canned blocks. With the `std` feature a
+[`Recorder`] captures the requests made to a real node in a file that a
+[`Replayer`] answers from later on, so tests written against a live chain can
+run offline.
+*/
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::ops::Bound;
+use no_std_async::Mutex;
+
+use crate::hasher::hash;
+use crate::meta_ext::{BlockInfo, Hasher, Meta as _, StorageKey};
+use crate::prelude::*;
+use crate::{
+    parse_uri, Backend, ConfirmationLevel, Error, JsonValue, Metadata, RawKey, RawValue, Result,
+    TxInfo, TxStatus,
+};
+
+/// In-memory backend for tests
+///
+/// Clones share the extrinsics submitted to any of them.
+#[derive(Clone)]
+pub struct MockBackend {
+    meta: Metadata,
+    storage: BTreeMap<RawKey, RawValue>,
+    blocks: BTreeMap<u64, BlockInfo>,
+    runtime_calls: BTreeMap<String, Vec<u8>>,
+    extrinsics: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl MockBackend {
+    /// A chain with the given metadata and empty storage whose only block is the genesis
+    pub fn new(meta: Metadata) -> Self {
+        let genesis = BlockInfo {
+            number: 0,
+            hash: [0; 32],
+            parent: [0; 32],
+        };
+        MockBackend {
+            meta,
+            storage: BTreeMap::new(),
+            blocks: BTreeMap::from([(0, genesis)]),
+            runtime_calls: BTreeMap::new(),
+            extrinsics: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Seed the storage with a JSON object whose keys are storage paths like
+    /// `system/account/0x...` with JSON values, or hex encoded raw keys with
+    /// hex encoded values
+    pub fn with_storage(mut self, storage: JsonValue) -> Result<Self> {
+        let JsonValue::Object(entries) = storage else {
+            return Err(Error::BadInput);
+        };
+        for (key, value) in entries {
+            self.insert(&key, value)?;
+        }
+        Ok(self)
+    }
+
+    /// Add a block, the one with the highest number is the latest
+    pub fn with_block(mut self, block: BlockInfo) -> Self {
+        self.blocks.insert(block.number, block);
+        self
+    }
+
+    /// SCALE encoded output of a runtime API method like `Core_version`
+    /// regardless of its arguments
+    pub fn with_runtime_call(mut self, method: &str, output: Vec<u8>) -> Self {
+        self.runtime_calls.insert(method.into(), output);
+        self
+    }
+
+    /// Set the value of a storage path or raw key like [`MockBackend::with_storage`],
+    /// a `null` value removes it
+    pub fn insert(&mut self, key: &str, value: JsonValue) -> Result<()> {
+        let (key, ty) = match key.strip_prefix("0x") {
+            Some(raw) => (hex::decode(raw).map_err(|_| Error::BadKey)?, None),
+            None => {
+                let (pallet, item, keys) = parse_uri(key).ok_or(Error::BadInput)?;
+                let pallet = self
+                    .meta
+                    .pallet_by_name(&pallet)
+                    .ok_or(Error::PalletNotFound(pallet))?;
+                let key =
+                    StorageKey::build_with_registry(self.meta.registry(), pallet, &item, &keys)?;
+                if key.is_partial() {
+                    return Err(Error::BadKey);
+                }
+                (key.key(), Some(key.ty))
+            }
+        };
+
+        let value = match (value, ty) {
+            (JsonValue::Null, _) => {
+                self.storage.remove(&key);
+                return Ok(());
+            }
+            (JsonValue::String(value), None) => {
+                hex::decode(value.trim_start_matches("0x")).map_err(|_| Error::BadInput)?
+            }
+            (_, None) => return Err(Error::BadInput),
+            (value, Some(ty)) => {
+                scales::to_vec_with_info(&value, (self.meta.registry(), ty).into())
+                    .map_err(|e| Error::Encode(e.to_string()))?
+            }
+        };
+        self.storage.insert(key, value);
+        Ok(())
+    }
+
+    /// Extrinsics submitted so far in the order they were submitted
+    pub async fn extrinsics(&self) -> Vec<Vec<u8>> {
+        self.extrinsics.lock().await.clone()
+    }
+
+    fn latest(&self) -> &BlockInfo {
+        self.blocks
+            .values()
+            .next_back()
+            .expect("there is always a genesis block")
+    }
+}
+
+/// The storage is the same at every block
+impl Backend for MockBackend {
+    async fn get_storage_items(
+        &self,
+        keys: Vec<RawKey>,
+        _block: Option<u32>,
+    ) -> Result<impl Iterator<Item = (RawKey, Option<RawValue>)>> {
+        Ok(keys
+            .into_iter()
+            .map(|k| {
+                let value = self.storage.get(&k).cloned();
+                (k, value)
+            })
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    async fn get_keys_paged(
+        &self,
+        prefix: RawKey,
+        size: u16,
+        start_key: Option<RawKey>,
+        _block: Option<u32>,
+    ) -> Result<Vec<RawKey>> {
+        let start = match start_key {
+            Some(key) => Bound::Excluded(key),
+            None => Bound::Included(prefix.clone()),
+        };
+        Ok(self
+            .storage
+            .range((start, Bound::Unbounded))
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(&prefix))
+            .take(size.into())
+            .cloned()
+            .collect())
+    }
+
+    async fn submit(&self, ext: impl AsRef<[u8]>) -> Result<()> {
+        self.extrinsics.lock().await.push(ext.as_ref().to_vec());
+        Ok(())
+    }
+
+    /// Extrinsics are finalized in the latest block as soon as they're submitted
+    async fn submit_and_watch(
+        &self,
+        ext: impl AsRef<[u8]>,
+        _level: ConfirmationLevel,
+    ) -> Result<TxInfo> {
+        let ext = ext.as_ref();
+        self.submit(ext).await?;
+        Ok(TxInfo {
+            hash: format!("0x{}", hex::encode(hash(&Hasher::Blake2_256, ext))),
+            status: TxStatus::Finalized(format!("0x{}", hex::encode(self.latest().hash))),
+        })
+    }
+
+    async fn metadata(&self) -> Result<Metadata> {
+        Ok(self.meta.clone())
+    }
+
+    async fn block_info(&self, at: Option<u32>) -> Result<BlockInfo> {
+        match at {
+            Some(number) => self
+                .blocks
+                .get(&number.into())
+                .cloned()
+                .ok_or(Error::BadBlockNumber),
+            None => Ok(self.latest().clone()),
+        }
+    }
+
+    async fn runtime_call(
+        &self,
+        method: &str,
+        _args: &[u8],
+        _block: Option<u32>,
+    ) -> Result<Vec<u8>> {
+        self.runtime_calls
+            .get(method)
+            .cloned()
+            .ok_or_else(|| Error::Node(format!("{} is not mocked", method)))
+    }
+
+    /// Submitted extrinsics are in the latest block, older blocks are empty
+    async fn block_extrinsics(&self, at: Option<u32>) -> Result<Vec<Vec<u8>>> {
+        let block = self.block_info(at).await?;
+        if block.number == self.latest().number {
+            Ok(self.extrinsics().await)
+        } else {
+            Ok(vec![])
+        }
+    }
+}
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+pub use recording::{Recorder, Recording, Replayer};
+
+#[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+mod recording {
+    use std::collections::BTreeMap;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use jsonrpc::serde_json::value::to_raw_value;
+    use serde::{Deserialize, Serialize};
+
+    #[cfg(feature = "ws")]
+    use crate::rpc::Subscription;
+    use crate::rpc::{error, Rpc, RpcResult};
+    use crate::JsonValue;
+
+    /// Where the client records the traffic of every chain or replays it from,
+    /// each chain gets its own file in the given directory
+    #[derive(Clone, Debug)]
+    pub enum Recording {
+        Record(PathBuf),
+        Replay(PathBuf),
+    }
+
+    impl Recording {
+        pub(crate) fn file(&self, chain: &str) -> PathBuf {
+            let (Recording::Record(dir) | Recording::Replay(dir)) = self;
+            let name = chain.replace("://", "_").replace(
+                |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+                "_",
+            );
+            dir.join(name).with_extension("jsonl")
+        }
+    }
+
+    // A request and its response, saved as a line of JSON
+    #[derive(Serialize, Deserialize)]
+    struct Entry {
+        method: String,
+        params: Vec<JsonValue>,
+        #[serde(flatten)]
+        response: Response,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Response {
+        Result(JsonValue),
+        Error(error::RpcError),
+    }
+
+    fn params(params: &[&str]) -> Vec<JsonValue> {
+        params
+            .iter()
+            .map(|p| serde_json::from_str(p).unwrap_or_else(|_| JsonValue::String(p.to_string())))
+            .collect()
+    }
+
+    // Requests are matched by method and parameters
+    fn request_key(method: &str, params: &[JsonValue]) -> String {
+        format!("{}{}", method, JsonValue::from(params.to_vec()))
+    }
+
+    /// Forwards requests to a node and saves them with their responses to a file,
+    /// subscriptions are not recorded
+    pub struct Recorder<R> {
+        rpc: R,
+        file: Mutex<File>,
+    }
+
+    impl<R: Rpc> Recorder<R> {
+        /// Start recording to the given file, it's replaced if it exists
+        pub fn new(rpc: R, path: impl AsRef<Path>) -> io::Result<Self> {
+            if let Some(dir) = path.as_ref().parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(path)?;
+            Ok(Recorder {
+                rpc,
+                file: Mutex::new(file),
+            })
+        }
+
+        fn record(&self, method: &str, params: &[&str], res: &RpcResult<JsonValue>) {
+            let response = match res {
+                Ok(result) => Response::Result(result.clone()),
+                Err(error::Error::Rpc(err)) => Response::Error(err.clone()),
+                // errors reaching the node are not part of its behavior
+                Err(_) => return,
+            };
+            let entry = Entry {
+                method: method.into(),
+                params: self::params(params),
+                response,
+            };
+            let line = serde_json::to_string(&entry).expect("entries are valid JSON");
+            let mut file = self.file.lock().expect("not poisoned");
+            if let Err(err) = writeln!(file, "{}", line) {
+                log::warn!("can't record `{}`: {}", method, err);
+            }
+        }
+    }
+
+    impl<R: Rpc> Rpc for Recorder<R> {
+        async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let res = self.rpc.rpc::<JsonValue>(method, params).await;
+            self.record(method, params, &res);
+            Ok(serde_json::from_value(res?)?)
+        }
+
+        async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let results = self.rpc.rpc_batch::<JsonValue>(requests).await?;
+            Ok(requests
+                .iter()
+                .zip(results)
+                .map(|((method, params), res)| {
+                    self.record(method, params, &res);
+                    Ok(serde_json::from_value(res?)?)
+                })
+                .collect())
+        }
+
+        #[cfg(feature = "ws")]
+        async fn subscribe(
+            &self,
+            method: &str,
+            params: &[&str],
+            unsubscribe: &str,
+        ) -> RpcResult<Subscription> {
+            self.rpc.subscribe(method, params, unsubscribe).await
+        }
+    }
+
+    /// Answers requests with the responses saved by a [`Recorder`]
+    ///
+    /// Requests made several times get their responses in the order they were
+    /// recorded, the last one is repeated once they run out.
+    pub struct Replayer {
+        responses: BTreeMap<String, Vec<Response>>,
+        replayed: Mutex<BTreeMap<String, usize>>,
+    }
+
+    impl Replayer {
+        pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+            let mut responses = BTreeMap::<_, Vec<_>>::new();
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry = serde_json::from_str::<Entry>(&line)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                responses
+                    .entry(request_key(&entry.method, &entry.params))
+                    .or_default()
+                    .push(entry.response);
+            }
+            Ok(Replayer {
+                responses,
+                replayed: Mutex::new(BTreeMap::new()),
+            })
+        }
+    }
+
+    impl Rpc for Replayer {
+        async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let key = request_key(method, &self::params(params));
+            let Some(responses) = self.responses.get(&key) else {
+                log::warn!("no recorded response for {}", key);
+                let err = to_raw_value(&format!("no recorded response for {}", key))?;
+                return Err(
+                    error::standard_error(error::StandardError::InternalError, Some(err)).into(),
+                );
+            };
+
+            let index = {
+                let mut replayed = self.replayed.lock().expect("not poisoned");
+                let count = replayed.entry(key).or_default();
+                *count += 1;
+                (*count - 1).min(responses.len() - 1)
+            };
+            match &responses[index] {
+                Response::Result(result) => Ok(serde_json::from_value(result.clone())?),
+                Response::Error(err) => Err(err.clone().into()),
+            }
+        }
+    }
+}
+
+/// Metadata of the chains the tests of the crate run against
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use crate::Metadata;
+    use codec::Decode;
+
+    pub(crate) const KREIVO: &[u8] = include_bytes!("../../../sdk/js/.papi/metadata/kreivo.scale");
+    pub(crate) const KUSAMA: &[u8] = include_bytes!("../../../sdk/js/.papi/metadata/kusama.scale");
+
+    pub(crate) fn kreivo() -> Metadata {
+        Metadata::decode(&mut &KREIVO[..]).expect("valid metadata")
+    }
+
+    pub(crate) fn kusama() -> Metadata {
+        Metadata::decode(&mut &KUSAMA[..]).expect("valid metadata")
+    }
+
+    /// Kreivo as if its metadata declared the version 5 extrinsic format
+    pub(crate) fn kreivo_v5() -> Metadata {
+        let mut meta = kreivo();
+        match &mut meta {
+            #[cfg(feature = "v14")]
+            Metadata::V14(m) => m.extrinsic.version = 5,
+            #[cfg(feature = "v15")]
+            Metadata::V15(m) => m.extrinsic.version = 5,
+        }
+        meta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, Response};
+
+    const ALICE: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    const BOB: &str = "0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48";
+
+    fn mock() -> MockBackend {
+        MockBackend::new(fixtures::kreivo())
+    }
+
+    // numbers are given as strings, the arbitrary precision numbers of
+    // serde_json are not understood by the serializer
+    fn account(free: u64) -> JsonValue {
+        json!({
+            "nonce": "3", "consumers": "0", "providers": "1", "sufficients": "0",
+            "data": { "free": free.to_string(), "reserved": "0", "frozen": "0", "flags": "0" }
+        })
+    }
+
+    #[async_std::test]
+    async fn query_seeded_storage() {
+        let chain = mock()
+            .with_storage(json!({
+                format!("system/account/{}", ALICE): account(100),
+                format!("system/account/{}", BOB): account(200),
+                "0x0102": "0x0304",
+            }))
+            .unwrap();
+        let meta = chain.meta.clone();
+
+        let path = format!("system/account/{}", ALICE);
+        let Response::Value(alice) = crate::query(&chain, &meta, &path, None, 0, None)
+            .await
+            .unwrap()
+        else {
+            panic!("expected a value");
+        };
+        assert_eq!(JsonValue::from(alice)["data"]["free"], json!(100));
+
+        let Response::ValueSet(accounts) =
+            crate::query(&chain, &meta, "system/account", None, 0, None)
+                .await
+                .unwrap()
+        else {
+            panic!("expected the accounts");
+        };
+        assert_eq!(accounts.len(), 2);
+
+        let raw = chain.get_storage_item(vec![1, 2], None).await.unwrap();
+        assert_eq!(raw, Some(vec![3, 4]));
+        let missing = format!("system/account/{}", "0x".to_string() + &"00".repeat(32));
+        assert!(matches!(
+            crate::query(&chain, &meta, &missing, None, 0, None).await,
+            Ok(Response::None)
+        ));
+    }
+
+    #[async_std::test]
+    async fn mocked_chain_in_client() {
+        let chain = mock()
+            .with_storage(json!({ format!("system/account/{}", ALICE): account(100) }))
+            .unwrap();
+        let client = crate::SubeClient::new()
+            .with_mock("kreivo.mock", chain)
+            .unwrap();
+        let url = format!("wss://kreivo.mock/system/account/{}", ALICE);
+
+        let res = crate::SubeBuilder::default()
+            .with_client(&client)
+            .with_url(&url)
+            .await
+            .unwrap();
+        let Response::Value(alice) = res else {
+            panic!("expected a value");
+        };
+        assert_eq!(JsonValue::from(alice)["nonce"], json!(3));
+    }
+
+    #[async_std::test]
+    async fn record_submitted_extrinsics() {
+        let chain = mock().with_block(BlockInfo {
+            number: 10,
+            hash: [10; 32],
+            parent: [9; 32],
+        });
+
+        chain.submit([1, 2, 3]).await.unwrap();
+        let info = chain
+            .submit_and_watch([4, 5], ConfirmationLevel::Finalized)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            chain.clone().extrinsics().await,
+            vec![vec![1, 2, 3], vec![4, 5]]
+        );
+        assert!(info.status.reached(ConfirmationLevel::Finalized));
+        assert_eq!(chain.block_info(None).await.unwrap().number, 10);
+        assert_eq!(chain.block_info(Some(0)).await.unwrap().hash, [0; 32]);
+        assert!(chain.block_info(Some(5)).await.is_err());
+    }
+
+    #[cfg(all(feature = "std", any(feature = "http", feature = "ws")))]
+    #[async_std::test]
+    async fn replay_recorded_requests() {
+        use crate::rpc::{self, Rpc, RpcResult};
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        struct Node(AtomicU32);
+        impl Rpc for Node {
+            async fn rpc<T>(&self, method: &str, _: &[&str]) -> RpcResult<T>
+            where
+                T: for<'de> serde::Deserialize<'de>,
+            {
+                match method {
+                    "system_unknown" => Err(rpc::error::standard_error(
+                        rpc::error::StandardError::MethodNotFound,
+                        None,
+                    )
+                    .into()),
+                    _ => Ok(serde_json::from_value(json!(self
+                        .0
+                        .fetch_add(1, Ordering::Relaxed)))?),
+                }
+            }
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("sube-recording-{}.jsonl", std::process::id()));
+        let recorder = Recorder::new(Node(AtomicU32::new(1)), &path).unwrap();
+        assert_eq!(
+            recorder.rpc::<u32>("chain_getHeader", &[]).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            recorder.rpc::<u32>("chain_getHeader", &[]).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            recorder
+                .rpc::<u32>("chain_getBlockHash", &["0"])
+                .await
+                .unwrap(),
+            3
+        );
+        assert!(recorder.rpc::<u32>("system_unknown", &[]).await.is_err());
+
+        let replayer = Replayer::load(&path).unwrap();
+        assert_eq!(
+            replayer
+                .rpc::<u32>("chain_getBlockHash", &["0"])
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            replayer.rpc::<u32>("chain_getHeader", &[]).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            replayer.rpc::<u32>("chain_getHeader", &[]).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            replayer.rpc::<u32>("chain_getHeader", &[]).await.unwrap(),
+            2
+        );
+        assert!(replayer.rpc::<u32>("system_unknown", &[]).await.is_err());
+        assert!(replayer
+            .rpc::<u32>("chain_getBlockHash", &["1"])
+            .await
+            .is_err());
+    }
+}
@@ -7,16 +7,77 @@ use crate::meta::{self, Metadata};
 use crate::Backend;
 use crate::Error;
 use crate::{prelude::*, RawKey as RawStorageKey, StorageChangeSet};
+#[cfg(feature = "ws")]
+use crate::{ConfirmationLevel, TxInfo, TxStatus};
 use meta::from_bytes;
 
 pub type RpcResult<T> = Result<T, error::Error>;
 
+// end of the range of implementation defined server errors of the JSONRpc spec,
+// nodes use the start of the range for calls that failed
+const CONNECTION_ERROR: i32 = -32099;
+
+/// Error of a request that didn't reach the node or whose response was lost
+pub fn connection_error(message: &str) -> error::RpcError {
+    error::RpcError {
+        code: CONNECTION_ERROR,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// Whether the node couldn't be reached as opposed to failing to process the request
+pub fn is_connection_error(err: &error::Error) -> bool {
+    match err {
+        error::Error::Transport(_) => true,
+        error::Error::Rpc(err) => err.code == CONNECTION_ERROR,
+        _ => false,
+    }
+}
+
+// bytes of a `0x` prefixed hex string of a response
+fn from_hex(res: &str) -> Option<Vec<u8>> {
+    hex::decode(res.strip_prefix("0x")?).ok()
+}
+
 /// Rpc defines types of backends that are remote and talk JSONRpc
 pub trait Rpc {
     async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
     where
         T: for<'de> Deserialize<'de>;
 
+    /// Send several requests at once, results are in the same order as the requests.
+    /// Backends that don't support JSONRpc batches send the requests concurrently
+    async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        Ok(futures_util::future::join_all(
+            requests
+                .iter()
+                .map(|(method, params)| self.rpc(method, params)),
+        )
+        .await)
+    }
+
+    /// Subscribe to the notifications of a JSONRpc pub/sub method, the subscription
+    /// is cancelled with the `unsubscribe` method once dropped
+    #[cfg(feature = "ws")]
+    async fn subscribe(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> RpcResult<Subscription> {
+        log::warn!(
+            "Can't subscribe to `{}`/`{}` with the current backend {:?}",
+            method,
+            unsubscribe,
+            params
+        );
+        Err(error::standard_error(error::StandardError::MethodNotFound, None).into())
+    }
+
     fn convert_params(params: &[&str]) -> Vec<Box<RawValue>> {
         params
             .iter()
@@ -27,85 +88,281 @@ pub trait Rpc {
     }
 }
 
+/// Stream of the notifications pushed by the node for a subscription
+#[cfg(feature = "ws")]
+pub struct Subscription {
+    rx: futures_channel::mpsc::UnboundedReceiver<serde_json::Value>,
+    unsubscribe: Option<Box<dyn FnOnce() + Send>>,
+}
+
+#[cfg(feature = "ws")]
+impl Subscription {
+    pub fn new(
+        rx: futures_channel::mpsc::UnboundedReceiver<serde_json::Value>,
+        unsubscribe: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Subscription {
+            rx,
+            unsubscribe: Some(Box::new(unsubscribe)),
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl futures_util::Stream for Subscription {
+    type Item = serde_json::Value;
+
+    fn poll_next(
+        mut self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Self::Item>> {
+        use futures_util::StreamExt;
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(feature = "ws")]
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe.take() {
+            unsubscribe();
+        }
+    }
+}
+
+/// Maximum number of storage keys queried with a single request
+pub const MAX_KEYS_PER_QUERY: usize = 100;
+
 pub struct RpcClient<R>(pub R);
 
-impl<R: Rpc> Backend for RpcClient<R> {
-    async fn get_storage_items(
+impl<R: Rpc> RpcClient<R> {
+    /// Hash of the block with the given number as a JSON string
+    async fn block_hash(&self, number: u32) -> crate::Result<String> {
+        let hash = self
+            .0
+            .rpc::<Option<String>>("chain_getBlockHash", &[&number.to_string()])
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))?
+            .ok_or(Error::BadBlockNumber)?;
+        Ok(format!("\"{}\"", hash))
+    }
+}
+
+#[cfg(feature = "ws")]
+impl<R: Rpc> RpcClient<R> {
+    /// Submit an extrinsic and follow its status in the transaction pool,
+    /// returns the hash of the extrinsic and a stream of its status updates
+    pub async fn watch_extrinsic(
         &self,
-        keys: Vec<RawStorageKey>,
-        block: Option<u32>,
-    ) -> crate::Result<impl Iterator<Item = (Vec<u8>, Option<Vec<u8>>)>> {
+        ext: impl AsRef<[u8]>,
+    ) -> crate::Result<(
+        String,
+        impl futures_util::Stream<Item = crate::Result<TxStatus>>,
+    )> {
+        use blake2::{digest::typenum::U32, Blake2b, Digest};
+        use futures_util::StreamExt;
+
+        let extrinsic = format!("0x{}", hex::encode(ext.as_ref()));
+        log::debug!("Extrinsic: {}", extrinsic);
+        let hash = format!("0x{}", hex::encode(Blake2b::<U32>::digest(ext.as_ref())));
+
+        let updates = self
+            .0
+            .subscribe(
+                "author_submitAndWatchExtrinsic",
+                &[&format!("\"{}\"", &extrinsic)],
+                "author_unwatchExtrinsic",
+            )
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))?
+            .map(|status| {
+                log::debug!("Extrinsic status: {}", status);
+                serde_json::from_value::<TxStatus>(status)
+                    .map_err(|e| crate::Error::Node(e.to_string()))
+            });
+
+        Ok((hash, updates))
+    }
+
+    async fn subscription(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> crate::Result<Subscription> {
+        self.0
+            .subscribe(method, params, unsubscribe)
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))
+    }
+
+    /// Follow the changes of the given storage keys, the first notification
+    /// contains their current values
+    pub async fn subscribe_storage(
+        &self,
+        keys: &[RawStorageKey],
+    ) -> crate::Result<
+        impl futures_util::Stream<Item = crate::Result<Vec<(RawStorageKey, Option<Vec<u8>>)>>> + 'static,
+    > {
+        use futures_util::StreamExt;
+
         let keys = serde_json::to_string(
             &keys
                 .iter()
-                .map(|v| format!("0x{}", hex::encode(v)))
-                .collect::<Vec<String>>(),
+                .map(|k| format!("0x{}", hex::encode(k)))
+                .collect::<Vec<_>>(),
         )
         .expect("it to be a valid json");
 
-        let params: Vec<String> = if let Some(block_number) = block {
-            let info = self
-                .block_info(Some(block_number))
-                .await
-                .map_err(|_| Error::BadBlockNumber)?;
+        let changes = self
+            .subscription(
+                "state_subscribeStorage",
+                &[&keys],
+                "state_unsubscribeStorage",
+            )
+            .await?
+            .map(|changes| {
+                let change_set = serde_json::from_value::<StorageChangeSet>(changes)
+                    .map_err(|e| crate::Error::Node(e.to_string()))?;
+                log::debug!("storage changes at {}", change_set.block);
+                change_set
+                    .changes
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let k = hex::decode(k.trim_start_matches("0x"));
+                        let v = v.map(|v| hex::decode(v.trim_start_matches("0x")));
+                        Ok((k?, v.transpose()?))
+                    })
+                    .collect::<Result<Vec<_>, hex::FromHexError>>()
+                    .map_err(|_| crate::Error::StorageKeyNotFound)
+            });
+
+        Ok(changes)
+    }
 
-            vec![keys, format!("\"0x{}\"", hex::encode(info.hash))]
+    /// Follow the headers of new blocks as they are imported or finalized
+    pub async fn subscribe_heads(&self, finalized: bool) -> crate::Result<Subscription> {
+        if finalized {
+            self.subscription(
+                "chain_subscribeFinalizedHeads",
+                &[],
+                "chain_unsubscribeFinalizedHeads",
+            )
+            .await
         } else {
-            vec![keys]
+            self.subscription("chain_subscribeNewHeads", &[], "chain_unsubscribeNewHeads")
+                .await
+        }
+    }
+
+    /// Follow the runtime version of the chain, the first notification
+    /// is the current version
+    pub async fn subscribe_runtime_version(&self) -> crate::Result<Subscription> {
+        self.subscription(
+            "state_subscribeRuntimeVersion",
+            &[],
+            "state_unsubscribeRuntimeVersion",
+        )
+        .await
+    }
+}
+
+impl<R: Rpc> Backend for RpcClient<R> {
+    /// Keys are queried in chunks of [`MAX_KEYS_PER_QUERY`] that are sent together
+    /// in a single batch
+    async fn get_storage_items(
+        &self,
+        keys: Vec<RawStorageKey>,
+        block: Option<u32>,
+    ) -> crate::Result<impl Iterator<Item = (Vec<u8>, Option<Vec<u8>>)>> {
+        if keys.is_empty() {
+            return Ok(vec![].into_iter());
+        }
+
+        let at = match block {
+            Some(block_number) => Some(
+                self.block_hash(block_number)
+                    .await
+                    .map_err(|_| Error::BadBlockNumber)?,
+            ),
+            None => None,
         };
 
-        let result = self
+        let params = keys
+            .chunks(MAX_KEYS_PER_QUERY)
+            .map(|keys| {
+                let keys = serde_json::to_string(
+                    &keys
+                        .iter()
+                        .map(|v| format!("0x{}", hex::encode(v)))
+                        .collect::<Vec<String>>(),
+                )
+                .expect("it to be a valid json");
+                [Some(keys), at.clone()].into_iter().flatten().collect()
+            })
+            .collect::<Vec<Vec<String>>>();
+        let params = params
+            .iter()
+            .map(|p| p.iter().map(|s| s.as_ref()).collect())
+            .collect::<Vec<Vec<&str>>>();
+        let requests = params
+            .iter()
+            .map(|p| ("state_queryStorageAt", p.as_slice()))
+            .collect::<Vec<_>>();
+
+        let results = self
             .0
-            .rpc::<Vec<StorageChangeSet>>(
-                "state_queryStorageAt",
-                params
-                    .iter()
-                    .map(|s| s.as_ref())
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            )
+            .rpc_batch::<Vec<StorageChangeSet>>(&requests)
             .await
+            .and_then(|results| results.into_iter().collect::<RpcResult<Vec<_>>>())
             .map_err(|err| {
                 log::error!("error state_queryStorageAt {:?}", err);
                 crate::Error::StorageKeyNotFound
             })?;
 
-        let result = match result.into_iter().next() {
-            None => vec![],
-            Some(change_set) => change_set
-                .changes
-                .into_iter()
-                .map(|(k, v)| {
-                    log::debug!("key: {:?} value: {:?}", k, v);
-
-                    (
-                        hex::decode(&k[2..]).expect("to be an hex"),
-                        v.map(|v| hex::decode(&v[2..]).expect("to be an hex")),
-                    )
-                })
-                .collect(),
-        };
+        let result = results
+            .into_iter()
+            .filter_map(|change_sets| change_sets.into_iter().next())
+            .flat_map(|change_set| change_set.changes)
+            .map(|(k, v)| {
+                log::debug!("key: {:?} value: {:?}", k, v);
+
+                (
+                    hex::decode(&k[2..]).expect("to be an hex"),
+                    v.map(|v| hex::decode(&v[2..]).expect("to be an hex")),
+                )
+            })
+            .collect::<Vec<_>>();
 
         Ok(result.into_iter())
     }
 
     async fn get_keys_paged(
         &self,
-        from: RawStorageKey,
+        prefix: RawStorageKey,
         size: u16,
-        to: Option<RawStorageKey>,
+        start_key: Option<RawStorageKey>,
+        block: Option<u32>,
     ) -> crate::Result<Vec<RawStorageKey>> {
+        let mut params = vec![format!("\"0x{}\"", hex::encode(&prefix)), size.to_string()];
+        // keys are returned starting after `start_key`, from the first key with the prefix if not set
+        let start_key = start_key.map(|k| format!("\"0x{}\"", hex::encode(k)));
+        if let Some(block) = block {
+            params.push(start_key.unwrap_or_else(|| "null".into()));
+            params.push(self.block_hash(block).await?);
+        } else if let Some(start_key) = start_key {
+            params.push(start_key);
+        }
+
         let result: Vec<String> = self
             .0
             .rpc(
                 "state_getKeysPaged",
-                &[
-                    &format!("\"0x{}\"", hex::encode(&from)),
-                    &size.to_string(),
-                    &to.or(Some(from))
-                        .map(|f| format!("\"0x{}\"", hex::encode(f)))
-                        .unwrap(),
-                ],
+                params
+                    .iter()
+                    .map(|s| s.as_ref())
+                    .collect::<Vec<_>>()
+                    .as_slice(),
             )
             .await
             .map_err(|err| {
@@ -113,10 +370,10 @@ impl<R: Rpc> Backend for RpcClient<R> {
                 crate::Error::StorageKeyNotFound
             })?;
         log::info!("rpc call {:?}", result);
-        Ok(result
-            .into_iter()
-            .map(|k| hex::decode(&k[2..]).expect("to be an hex"))
-            .collect())
+        result
+            .iter()
+            .map(|k| from_hex(k).ok_or(Error::BadKey))
+            .collect()
     }
 
     async fn submit(&self, ext: impl AsRef<[u8]>) -> crate::Result<()> {
@@ -131,19 +388,139 @@ impl<R: Rpc> Backend for RpcClient<R> {
         Ok(())
     }
 
+    #[cfg(feature = "ws")]
+    async fn submit_and_watch(
+        &self,
+        ext: impl AsRef<[u8]>,
+        level: ConfirmationLevel,
+    ) -> crate::Result<TxInfo> {
+        use futures_util::StreamExt;
+
+        let (hash, mut updates) = self.watch_extrinsic(ext).await?;
+        while let Some(status) = updates.next().await {
+            let status = status?;
+            if status.reached(level) {
+                return Ok(TxInfo { hash, status });
+            }
+            if status.is_final() {
+                return Err(crate::Error::ExtrinsicDropped(status));
+            }
+        }
+        Err(crate::Error::Node(
+            "Extrinsic status subscription closed".into(),
+        ))
+    }
+
     async fn metadata(&self) -> crate::Result<Metadata> {
+        // V15 is only available through the `Metadata_metadata_at_version` runtime API,
+        // fallback to the default metadata of the node when it's not supported
+        #[cfg(feature = "v15")]
+        {
+            use codec::{Decode, Encode};
+            let version = format!("\"0x{}\"", hex::encode(15u32.encode()));
+            let res = self
+                .0
+                .rpc::<String>(
+                    "state_call",
+                    &["\"Metadata_metadata_at_version\"", &version],
+                )
+                .await;
+
+            match res {
+                Ok(res) => {
+                    let response = from_hex(&res).ok_or(crate::Error::BadMetadata)?;
+                    let opaque = Option::<Vec<u8>>::decode(&mut response.as_slice())
+                        .map_err(|_| crate::Error::BadMetadata)?;
+                    if let Some(bytes) = opaque {
+                        let meta = from_bytes(&mut bytes.as_slice())
+                            .map_err(|_| crate::Error::BadMetadata)?;
+                        log::trace!("Metadata {:#?}", meta);
+                        return Ok(meta);
+                    }
+                }
+                Err(err) => log::debug!("V15 metadata not available {:?}", err),
+            }
+        }
+
         let res: String = self
             .0
             .rpc("state_getMetadata", &[])
             .await
             .map_err(|e| crate::Error::Node(e.to_string()))?;
-        let response =
-            hex::decode(&res[2..]).map_err(|_err| crate::Error::CantDecodeReponseForMeta)?;
+        let response = from_hex(&res).ok_or(crate::Error::CantDecodeReponseForMeta)?;
         let meta = from_bytes(&mut response.as_slice()).map_err(|_| crate::Error::BadMetadata)?;
         log::trace!("Metadata {:#?}", meta);
         Ok(meta)
     }
 
+    async fn runtime_call(
+        &self,
+        method: &str,
+        args: &[u8],
+        block: Option<u32>,
+    ) -> crate::Result<Vec<u8>> {
+        let mut params = vec![
+            format!("\"{}\"", method),
+            format!("\"0x{}\"", hex::encode(args)),
+        ];
+        if let Some(block_number) = block {
+            let info = self
+                .block_info(Some(block_number))
+                .await
+                .map_err(|_| Error::BadBlockNumber)?;
+            params.push(format!("\"0x{}\"", hex::encode(info.hash)));
+        }
+
+        let res = self
+            .0
+            .rpc::<String>(
+                "state_call",
+                params
+                    .iter()
+                    .map(|s| s.as_ref())
+                    .collect::<Vec<_>>()
+                    .as_slice(),
+            )
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))?;
+
+        hex::decode(res.trim_start_matches("0x")).map_err(|_| Error::CantDecodeRawQueryResponse)
+    }
+
+    async fn block_extrinsics(&self, at: Option<u32>) -> crate::Result<Vec<Vec<u8>>> {
+        #[derive(Deserialize)]
+        struct Block {
+            extrinsics: Vec<String>,
+        }
+        #[derive(Deserialize)]
+        struct SignedBlock {
+            block: Block,
+        }
+
+        let hash = match at {
+            Some(number) => Some(self.block_hash(number).await?),
+            None => None,
+        };
+        let params = hash.as_deref().into_iter().collect::<Vec<_>>();
+
+        let signed = self
+            .0
+            .rpc::<Option<SignedBlock>>("chain_getBlock", &params)
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))?
+            .ok_or(Error::BadBlockNumber)?;
+
+        signed
+            .block
+            .extrinsics
+            .iter()
+            .map(|ext| {
+                hex::decode(ext.trim_start_matches("0x"))
+                    .map_err(|_| Error::CantDecodeRawQueryResponse)
+            })
+            .collect()
+    }
+
     async fn block_info(&self, at: Option<u32>) -> crate::Result<meta::BlockInfo> {
         #[inline]
         async fn block_info(s: &impl Rpc, params: &[&str]) -> crate::Result<Vec<u8>> {
@@ -155,6 +532,13 @@ impl<R: Rpc> Backend for RpcClient<R> {
             Ok(hex::decode(&f?.as_str()[2..]).expect("to be an valid hex"))
         }
 
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Header {
+            parent_hash: String,
+            number: String,
+        }
+
         let block_hash = if let Some(block_number) = at {
             let block_number = block_number.to_string();
             block_info(&self.0, &[&block_number]).await?
@@ -162,14 +546,243 @@ impl<R: Rpc> Backend for RpcClient<R> {
             block_info(&self.0, &[]).await?
         };
 
+        let header = self
+            .0
+            .rpc::<Header>(
+                "chain_getHeader",
+                &[&format!("\"0x{}\"", hex::encode(&block_hash))],
+            )
+            .await
+            .map_err(|e| crate::Error::Node(e.to_string()))?;
+
+        let number = u64::from_str_radix(header.number.trim_start_matches("0x"), 16)
+            .map_err(|_| crate::Error::BadBlockNumber)?;
+        let parent: [u8; 32] = hex::decode(header.parent_hash.trim_start_matches("0x"))
+            .ok()
+            .and_then(|hash| hash.try_into().ok())
+            .ok_or_else(|| crate::Error::Node("Invalid parent hash".into()))?;
+
         Ok(meta::BlockInfo {
-            number: at.unwrap_or(0) as u64,
+            number,
             hash: block_hash[0..32]
                 .try_into()
                 .expect("Block hash is not 32 bytes"),
-            parent: block_hash[0..32]
-                .try_into()
-                .expect("Block hash is not 32 bytes"),
+            parent,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use serde_json::json;
+
+    use crate::mock::fixtures::{KREIVO, KUSAMA};
+
+    /// Node that answers storage queries with the key as value and records
+    /// the size of the batches it receives
+    #[derive(Default)]
+    struct Node {
+        batches: RefCell<Vec<usize>>,
+        /// Response to `Metadata_metadata_at_version`, not supported if `None`
+        metadata_at_version: Option<String>,
+        /// Sorted storage keys served by `state_getKeysPaged`
+        keys: Vec<String>,
+        /// Parameters of every `state_getKeysPaged` request
+        keys_paged: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl Rpc for Node {
+        async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let res = match method {
+                "chain_getBlockHash" => {
+                    json!(format!("0x{:064x}", params[0].parse::<u32>().unwrap()))
+                }
+                "state_queryStorageAt" => {
+                    let keys: Vec<String> = serde_json::from_str(params[0])?;
+                    let changes = keys.into_iter().map(|k| (k.clone(), Some(k)));
+                    json!([{ "block": params.get(1), "changes": changes.collect::<Vec<_>>() }])
+                }
+                "state_call" if params[0] == "\"Metadata_metadata_at_version\"" => {
+                    assert_eq!(params[1], "\"0x0f000000\"");
+                    let Some(res) = &self.metadata_at_version else {
+                        return Err(error::Error::Rpc(error::RpcError {
+                            code: -32000,
+                            message: "Exported method Metadata_metadata_at_version is not found"
+                                .into(),
+                            data: None,
+                        }));
+                    };
+                    json!(res)
+                }
+                "state_getKeysPaged" => {
+                    self.keys_paged
+                        .borrow_mut()
+                        .push(params.iter().map(|p| p.to_string()).collect());
+                    let prefix: String = serde_json::from_str(params[0])?;
+                    let start: Option<String> = match params.get(2) {
+                        Some(start) => serde_json::from_str(start)?,
+                        None => None,
+                    };
+                    let keys = self
+                        .keys
+                        .iter()
+                        .filter(|k| k.starts_with(&prefix))
+                        .filter(|k| start.as_ref().is_none_or(|start| *k > start))
+                        .take(params[1].parse().unwrap())
+                        .collect::<Vec<_>>();
+                    json!(keys)
+                }
+                "state_getMetadata" => json!(format!("0x{}", hex::encode(KUSAMA))),
+                _ => unreachable!("unexpected request {}", method),
+            };
+            Ok(serde_json::from_value(res)?)
+        }
+
+        async fn rpc_batch<T>(&self, requests: &[(&str, &[&str])]) -> RpcResult<Vec<RpcResult<T>>>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            self.batches.borrow_mut().push(requests.len());
+            let mut results = vec![];
+            for (method, params) in requests {
+                results.push(self.rpc(method, params).await);
+            }
+            Ok(results)
+        }
+    }
+
+    #[async_std::test]
+    async fn storage_items_are_queried_in_a_batch() {
+        let chain = RpcClient(Node::default());
+        let keys = (0..250u32)
+            .map(|i| i.to_be_bytes().to_vec())
+            .collect::<Vec<_>>();
+
+        let items = chain
+            .get_storage_items(keys.clone(), Some(1))
+            .await
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(*chain.0.batches.borrow(), vec![3]);
+        assert_eq!(
+            items,
+            keys.into_iter()
+                .map(|k| (k.clone(), Some(k)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[async_std::test]
+    async fn no_keys_no_requests() {
+        let chain = RpcClient(Node::default());
+        let items = chain.get_storage_items(vec![], None).await.unwrap();
+
+        assert_eq!(items.count(), 0);
+        assert!(chain.0.batches.borrow().is_empty());
+    }
+
+    fn has_pallet(meta: &Metadata, name: &str) -> bool {
+        use crate::meta::Meta as _;
+        meta.pallet_by_name(name).is_some()
+    }
+
+    #[cfg(feature = "v15")]
+    #[async_std::test]
+    async fn metadata_v15_when_the_runtime_exposes_it() {
+        use codec::Encode;
+        let chain = RpcClient(Node {
+            metadata_at_version: Some(format!("0x{}", hex::encode(Some(KREIVO).encode()))),
+            ..Default::default()
+        });
+        let meta = chain.metadata().await.unwrap();
+        assert!(matches!(meta, Metadata::V15(_)));
+        assert!(has_pallet(&meta, "Pass"));
+    }
+
+    #[async_std::test]
+    async fn metadata_of_the_node_otherwise() {
+        let chain = RpcClient(Node::default());
+        let meta = chain.metadata().await.unwrap();
+        assert!(!has_pallet(&meta, "Pass"));
+
+        // runtime API exposed but without the version
+        let chain = RpcClient(Node {
+            metadata_at_version: Some("0x00".into()),
+            ..Default::default()
+        });
+        let meta = chain.metadata().await.unwrap();
+        assert!(!has_pallet(&meta, "Pass"));
+    }
+
+    #[cfg(feature = "v15")]
+    #[async_std::test]
+    async fn malformed_metadata_is_an_error() {
+        for res in ["", "0", "0xzz", "0x0102"] {
+            let chain = RpcClient(Node {
+                metadata_at_version: Some(res.into()),
+                ..Default::default()
+            });
+            assert!(matches!(
+                chain.metadata().await,
+                Err(crate::Error::BadMetadata)
+            ));
+        }
+    }
+
+    #[async_std::test]
+    async fn storage_entries_continue_after_the_last_key() {
+        use crate::hasher::hash;
+        use crate::meta::{Hasher, Meta as _, StorageKey};
+        use futures_util::TryStreamExt;
+
+        let meta = from_bytes(&mut &KREIVO[..]).unwrap();
+        let pallet = meta.pallet_by_name("System").unwrap();
+        let key =
+            StorageKey::build_with_registry(meta.registry(), pallet, "Account", &[] as &[&str])
+                .unwrap();
+        let account = |i: u32| [i.to_le_bytes().to_vec(), vec![0; 28]].concat();
+        let mut keys = (0..1500)
+            .map(|i| {
+                let account = account(i);
+                let hashed = hash(&Hasher::Blake2_128Concat, &account);
+                format!("0x{}", hex::encode([key.key(), hashed].concat()))
+            })
+            .collect::<Vec<_>>();
+        keys.sort();
+        let chain = RpcClient(Node {
+            keys: keys.clone(),
+            ..Default::default()
+        });
+
+        let entries = crate::storage_entries(&chain, &meta, key, Some(5))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1500);
+        let last_of_page = hex::decode(&keys[999][keys[999].len() - 64..]).unwrap();
+        assert_eq!(
+            serde_json::to_value(&entries[999].0).unwrap(),
+            json!([last_of_page])
+        );
+
+        // the second page starts after the last key of the first one,
+        // both pages are of the state at the same block
+        let requests = chain.0.keys_paged.borrow();
+        let at = format!("\"0x{:064x}\"", 5);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0][1..],
+            ["1000".to_string(), "null".into(), at.clone()]
+        );
+        assert_eq!(
+            requests[1][1..],
+            ["1000".to_string(), format!("\"{}\"", keys[999]), at]
+        );
+    }
+}
@@ -1,52 +1,151 @@
+use crate::prelude::*;
 use crate::Result;
 use core::{future::Future, marker::PhantomData};
 
 pub type Bytes<const N: usize> = [u8; N];
 
-/// Signed extrinsics need to be signed by a `Signer` before submission  
+/// Signature schemes of the `MultiSignature` type most chains use,
+/// the scheme of a signer selects the variant of the signature the runtime expects
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scheme {
+    Ed25519,
+    #[default]
+    Sr25519,
+    /// Recoverable ECDSA signature of 65 bytes
+    Ecdsa,
+}
+
+impl Scheme {
+    /// Name of the variant of the `MultiSignature` type for the scheme
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scheme::Ed25519 => "Ed25519",
+            Scheme::Sr25519 => "Sr25519",
+            Scheme::Ecdsa => "Ecdsa",
+        }
+    }
+
+    pub fn signature_len(&self) -> usize {
+        match self {
+            Scheme::Ed25519 | Scheme::Sr25519 => 64,
+            Scheme::Ecdsa => 65,
+        }
+    }
+}
+
+/// Signed extrinsics need to be signed by a `Signer` before submission
 pub trait Signer {
+    /// 32 byte public key or 20 byte Ethereum style account
     type Account: AsRef<[u8]>;
     type Signature: AsRef<[u8]>;
 
     fn sign(&self, data: impl AsRef<[u8]>) -> impl Future<Output = Result<Self::Signature>>;
 
     fn account(&self) -> Self::Account;
+
+    fn scheme(&self) -> Scheme {
+        Scheme::Sr25519
+    }
 }
 
-/// Wrapper to create a standard signer from an account and closure
-pub struct SignerFn<S, SF> {
-    account: Bytes<32>,
+/// Wrapper to create a standard signer from an account and closure,
+/// it signs with `Sr25519` unless another scheme is given
+pub struct SignerFn<S, SF, Sig = Bytes<64>> {
+    account: Vec<u8>,
+    scheme: Scheme,
     signer: S,
-    _fut: PhantomData<SF>,
+    _fut: PhantomData<(SF, Sig)>,
+}
+
+impl<S, SF, Sig> SignerFn<S, SF, Sig> {
+    pub fn with_scheme(self, scheme: Scheme) -> Self {
+        Self { scheme, ..self }
+    }
 }
 
-impl<S, SF> Signer for SignerFn<S, SF>
+impl<S, SF, Sig> Signer for SignerFn<S, SF, Sig>
 where
     S: Fn(&[u8]) -> SF,
-    SF: Future<Output = Result<Bytes<64>>>,
+    SF: Future<Output = Result<Sig>>,
+    Sig: AsRef<[u8]>,
 {
-    type Account = Bytes<32>;
-    type Signature = Bytes<64>;
+    type Account = Vec<u8>;
+    type Signature = Sig;
 
     fn sign(&self, data: impl AsRef<[u8]>) -> impl Future<Output = Result<Self::Signature>> {
         (self.signer)(data.as_ref())
     }
 
     fn account(&self) -> Self::Account {
-        self.account
+        self.account.clone()
+    }
+
+    fn scheme(&self) -> Scheme {
+        self.scheme
     }
 }
 
-impl<A: AsRef<[u8]>, S, SF> From<(A, S)> for SignerFn<S, SF>
+/// Accounts of signers are 32 byte public keys or 20 byte Ethereum style
+/// accounts, other lengths are rejected with [`Error::BadInput`](crate::Error)
+impl<A, S, SF, Sig> TryFrom<(A, S)> for SignerFn<S, SF, Sig>
 where
     A: AsRef<[u8]>,
     S: Fn(&[u8]) -> SF,
 {
-    fn from((account, signer): (A, S)) -> Self {
-        SignerFn {
-            account: account.as_ref().try_into().expect("32bit account"),
+    type Error = crate::Error;
+
+    fn try_from((account, signer): (A, S)) -> Result<Self> {
+        let account = account.as_ref();
+        if !matches!(account.len(), 20 | 32) {
+            return Err(crate::Error::BadInput);
+        }
+        Ok(SignerFn {
+            account: account.to_vec(),
+            scheme: Scheme::default(),
             signer,
             _fut: PhantomData,
+        })
+    }
+}
+
+/// Signer that doesn't sign, it produces an empty signature for the account
+/// of the wrapped signer. Useful to build extrinsics only to estimate their fees
+pub struct DummySigner<S>(pub S);
+
+impl<S: Signer> Signer for DummySigner<S> {
+    type Account = S::Account;
+    type Signature = Vec<u8>;
+
+    async fn sign(&self, _data: impl AsRef<[u8]>) -> Result<Self::Signature> {
+        Ok(vec![0; self.scheme().signature_len()])
+    }
+
+    fn account(&self) -> Self::Account {
+        self.0.account()
+    }
+
+    fn scheme(&self) -> Scheme {
+        self.0.scheme()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signer_accounts_have_a_valid_length() {
+        let sign = |_: &[u8]| async { Ok([0; 64]) };
+        let signer = SignerFn::try_from(([1; 32], sign)).unwrap();
+        assert_eq!(signer.account(), [1; 32]);
+        let signer = SignerFn::try_from(([2; 20], sign)).unwrap();
+        assert_eq!(signer.account(), [2; 20]);
+
+        for account in [&[3; 33][..], &[4; 31], &[]] {
+            assert!(matches!(
+                SignerFn::<_, _>::try_from((account, sign)),
+                Err(crate::Error::BadInput)
+            ));
         }
     }
 }
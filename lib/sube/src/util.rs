@@ -17,3 +17,8 @@ pub fn to_camel(term: &str) -> String {
     }
     result
 }
+
+/// Serialize bytes as a `0x` prefixed hex string
+pub fn serialize_hex<S: serde::Serializer>(bytes: &[u8], ser: S) -> Result<S::Ok, S::Error> {
+    ser.serialize_str(&format!("0x{}", hex::encode(bytes)))
+}
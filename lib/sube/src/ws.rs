@@ -1,8 +1,10 @@
 use alloc::{collections::BTreeMap, sync::Arc};
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
 
-use ewebsock::{WsEvent, WsMessage as Message, WsReceiver as Rx, WsSender as Tx};
+use async_std::{future::timeout, task::sleep};
 use futures_channel::{mpsc, oneshot};
-use futures_util::StreamExt as _;
+use futures_util::{future, stream, SinkExt as _, StreamExt as _};
 use no_std_async::Mutex;
 // use futures_util::StreamExt;
 use jsonrpc::{
@@ -18,155 +20,1030 @@ use async_std::task::spawn;
 use async_std::task::spawn_local as spawn;
 
 use crate::{
-    rpc::{self, Rpc, RpcResult},
+    prelude::*,
+    rpc::{self, connection_error, Rpc, RpcResult, Subscription},
     Error,
 };
 
-const MAX_BUFFER: usize = usize::MAX >> 3;
+// requests waiting to be sent before callers have to wait
+const MAX_QUEUED_REQUESTS: usize = 512;
 
 type Id = u32;
+type SubscriptionId = String;
+type Notifications = mpsc::UnboundedSender<serde_json::Value>;
+
+/// Message exchanged with the node
+#[derive(Clone, Debug)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Events of a websocket connection, it's opened in the background and
+/// reports when it's ready or failed
+#[derive(Clone, Debug)]
+pub enum WsEvent {
+    Opened,
+    Message(Message),
+    Error(String),
+    Closed,
+}
+
+/// Sending half of a websocket connection
+#[cfg(not(feature = "js"))]
+pub type WsTx = Box<dyn FnMut(Message) + Send>;
+#[cfg(feature = "js")]
+pub type WsTx = Box<dyn FnMut(Message)>;
+
+/// Events of a websocket connection
+#[cfg(not(feature = "js"))]
+pub type WsRx = futures_util::stream::BoxStream<'static, WsEvent>;
+#[cfg(feature = "js")]
+pub type WsRx = futures_util::stream::LocalBoxStream<'static, WsEvent>;
+
+/// Opens a connection to the node, the backend calls it again every time it reconnects
+#[cfg(not(feature = "js"))]
+pub trait Connect: Fn() -> Result<(WsTx, WsRx), String> + Send + 'static {}
+#[cfg(not(feature = "js"))]
+impl<F: Fn() -> Result<(WsTx, WsRx), String> + Send + 'static> Connect for F {}
+#[cfg(feature = "js")]
+pub trait Connect: Fn() -> Result<(WsTx, WsRx), String> + 'static {}
+#[cfg(feature = "js")]
+impl<F: Fn() -> Result<(WsTx, WsRx), String> + 'static> Connect for F {}
+
+/// Connection settings of the websocket backend
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Time to wait for the response of a request, `None` waits indefinitely
+    pub request_timeout: Option<Duration>,
+    /// Time to wait before reconnecting, it doubles after every failed attempt
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+    /// Failed attempts to reconnect before giving up, `None` retries indefinitely
+    pub max_reconnect_attempts: Option<u32>,
+    /// Send requests that weren't answered before the connection was lost again
+    /// once reconnected instead of failing them
+    pub resend_requests: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            request_timeout: Some(Duration::from_secs(30)),
+            reconnect_delay: Duration::from_millis(500),
+            max_reconnect_delay: Duration::from_secs(30),
+            max_reconnect_attempts: Some(10),
+            resend_requests: true,
+        }
+    }
+}
+
+// Requests waiting for a response, kept serialized so they can be sent again
+// after reconnecting. Subscriptions are re-established with requests nobody awaits.
+struct Pending {
+    request: String,
+    response: Option<oneshot::Sender<rpc::Response>>,
+    // the subscription the request creates
+    subscription: Option<Id>,
+    // the connection the request was sent through
+    sent: Option<u32>,
+}
+
+struct ActiveSubscription {
+    method: String,
+    params: Vec<String>,
+    unsubscribe: String,
+    notifications: Notifications,
+    // the node assigns a new id every time the subscription is created
+    id: Option<SubscriptionId>,
+}
+
+// Request ids are never reused, 0 is reserved for unsubscription
+// requests whose responses are not awaited
+#[derive(Default)]
+struct Ids(AtomicU32);
+
+impl Ids {
+    fn next(&self) -> Id {
+        loop {
+            let id = self.0.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    ids: Arc<Ids>,
+    requests: BTreeMap<Id, Pending>,
+    // subscriptions by the id of the request that created them
+    subscriptions: BTreeMap<Id, ActiveSubscription>,
+    subscription_ids: BTreeMap<SubscriptionId, Id>,
+    // number of the current connection
+    connection: u32,
+}
+
+enum Command {
+    Cancel(Id),
+    Unsubscribe(Id),
+}
+
+// Cancels a request when the future waiting for its response is dropped
+struct InFlight<'a> {
+    id: Id,
+    control: &'a mpsc::UnboundedSender<Command>,
+    answered: bool,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if !self.answered {
+            log::debug!("Cancelling request {}", self.id);
+            let _ = self.control.unbounded_send(Command::Cancel(self.id));
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Notification {
+    params: NotificationParams,
+}
+
+#[derive(Deserialize)]
+struct NotificationParams {
+    subscription: serde_json::Value,
+    result: serde_json::Value,
+}
+
+// Nodes can identify subscriptions with strings or numbers
+fn subscription_id(id: &serde_json::Value) -> SubscriptionId {
+    match id {
+        serde_json::Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
 
 pub struct Backend {
-    tx: Mutex<mpsc::Sender<Message>>,
-    ws_sender: Arc<Mutex<Tx>>,
-    messages: Arc<Mutex<BTreeMap<Id, oneshot::Sender<rpc::Response>>>>,
+    requests: Mutex<mpsc::Sender<Id>>,
+    // commands sent from destructors that can't wait
+    control: mpsc::UnboundedSender<Command>,
+    ids: Arc<Ids>,
+    state: Arc<Mutex<State>>,
+    options: Options,
 }
-unsafe impl Send for Backend {}
-unsafe impl Sync for Backend {}
 
 impl Rpc for Backend {
     async fn rpc<T>(&self, method: &str, params: &[&str]) -> RpcResult<T>
     where
         T: for<'a> Deserialize<'a>,
     {
-        let id = self.next_id().await;
-        info!("RPC `{}` (ID={})", method, id);
-
+        let mut request = self.in_flight();
         // Store a sender that will notify our receiver when a matching message arrives
         let (sender, recv) = oneshot::channel::<rpc::Response>();
-        let messages = self.messages.clone();
-        messages.lock().await.insert(id, sender);
+        self.send_request(request.id, method, params, sender, None)
+            .await?;
+
+        // wait for the matching response to arrive
+        let res = self.response(request.id, recv).await?;
+        request.answered = true;
+        res.result()
+    }
+
+    async fn subscribe(
+        &self,
+        method: &str,
+        params: &[&str],
+        unsubscribe: &str,
+    ) -> RpcResult<Subscription> {
+        let mut request = self.in_flight();
+        let id = request.id;
+        let (sender, recv) = oneshot::channel::<rpc::Response>();
+        let (notifications_tx, notifications_rx) = mpsc::unbounded();
+        self.send_request(
+            id,
+            method,
+            params,
+            sender,
+            Some((unsubscribe.into(), notifications_tx)),
+        )
+        .await?;
+
+        let res = self.response(id, recv).await?;
+        request.answered = true;
+        let sub = res.result::<serde_json::Value>()?;
+        log::debug!(
+            "Subscribed to `{}` with id {}",
+            method,
+            subscription_id(&sub)
+        );
+
+        // the node assigned id changes when reconnecting,
+        // subscriptions are cancelled by the id of their request
+        let control = self.control.clone();
+        Ok(Subscription::new(notifications_rx, move || {
+            log::debug!("Unsubscribing {}", id);
+            if let Err(err) = control.unbounded_send(Command::Unsubscribe(id)) {
+                log::warn!("Error unsubscribing: {:?}", err);
+            }
+        }))
+    }
+}
+
+impl Backend {
+    fn in_flight(&self) -> InFlight<'_> {
+        InFlight {
+            id: self.ids.next(),
+            control: &self.control,
+            answered: false,
+        }
+    }
 
-        // send rpc request
-        let msg = serde_json::to_string(&rpc::Request {
+    async fn send_request(
+        &self,
+        id: Id,
+        method: &str,
+        params: &[&str],
+        response: oneshot::Sender<rpc::Response>,
+        subscription: Option<(String, Notifications)>,
+    ) -> RpcResult<()> {
+        info!("RPC `{}` (ID={})", method, id);
+        let mut state = self.state.lock().await;
+
+        let request = Self::request(id, method, params);
+        log::debug!("RPC Request {} ...", &request);
+
+        let subscription = subscription.map(|(unsubscribe, notifications)| {
+            let sub = ActiveSubscription {
+                method: method.into(),
+                params: params.iter().map(|p| p.to_string()).collect(),
+                unsubscribe,
+                notifications,
+                id: None,
+            };
+            state.subscriptions.insert(id, sub);
+            id
+        });
+        state.requests.insert(
+            id,
+            Pending {
+                request,
+                response: Some(response),
+                subscription,
+                sent: None,
+            },
+        );
+        drop(state);
+
+        // waits for the queued requests to be sent when the queue is full
+        let sent = self.requests.lock().await.send(id).await;
+        if let Err(err) = sent {
+            log::error!("Error queueing request: {:?}", err);
+            self.state.lock().await.remove(id);
+            return Err(connection_error("Connection closed").into());
+        }
+        Ok(())
+    }
+
+    async fn response(
+        &self,
+        id: Id,
+        recv: oneshot::Receiver<rpc::Response>,
+    ) -> RpcResult<rpc::Response> {
+        let res = match self.options.request_timeout {
+            Some(t) => match timeout(t, recv).await {
+                Ok(res) => res,
+                Err(_) => {
+                    log::warn!("Request {} timed out", id);
+                    return Err(connection_error("Request timed out").into());
+                }
+            },
+            None => recv.await,
+        };
+
+        res.map_err(|err| {
+            log::error!("Error receiving message: {:?}", err);
+            standard_error(StandardError::InternalError, None).into()
+        })
+    }
+
+    fn request(id: Id, method: &str, params: &[&str]) -> String {
+        serde_json::to_string(&rpc::Request {
             id: id.into(),
             jsonrpc: Some("2.0"),
             method,
             params: &Self::convert_params(params),
         })
-        .expect("Request is serializable");
+        .expect("Request is serializable")
+    }
 
-        log::debug!("RPC Request {} ...", &msg);
+    pub async fn new_ws2<'a, U: Into<&'a str>>(url: U) -> core::result::Result<Self, Error> {
+        Self::connect(url, Options::default())
+    }
 
-        self.tx
-            .lock()
-            .await
-            .try_send(Message::Text(msg))
-            .map_err(|err| {
-                log::error!("Error tx lock message: {:?}", err);
-                standard_error(StandardError::InternalError, None)
-            })?;
+    /// Connect to the node at the given url with custom connection settings
+    pub fn connect<'a, U: Into<&'a str>>(
+        url: U,
+        options: Options,
+    ) -> core::result::Result<Self, Error> {
+        let url = url.into().to_string();
+        Self::with_connector(
+            move || {
+                log::trace!("WS connecting to {}", url);
+                open(&url)
+            },
+            options,
+        )
+    }
 
-        log::info!("sent CMD");
-        // wait for the matching response to arrive
-        let res = recv
-            .await
-            .map_err(|err| {
-                log::error!("Error receiving message: {:?}", err);
-                standard_error(StandardError::InternalError, None)
-            })?
-            .result()?;
+    /// Create a backend that opens its connections with the given function,
+    /// it allows using a different websocket implementation
+    pub fn with_connector(
+        connect: impl Connect,
+        options: Options,
+    ) -> core::result::Result<Self, Error> {
+        let connection = connect().map_err(Error::Platform)?;
+        let (requests_tx, requests) = mpsc::channel::<Id>(MAX_QUEUED_REQUESTS);
+        let (control, commands) = mpsc::unbounded::<Command>();
+        let state = State::default();
 
-        Ok(res)
+        let backend = Backend {
+            requests: Mutex::new(requests_tx),
+            control,
+            ids: state.ids.clone(),
+            state: Arc::new(Mutex::new(state)),
+            options,
+        };
+
+        spawn(Self::run(
+            connect,
+            connection,
+            requests,
+            commands,
+            backend.state.clone(),
+            backend.options.clone(),
+        ));
+        Ok(backend)
     }
-}
 
-impl Backend {
-    async fn next_id(&self) -> Id {
-        self.messages.lock().await.keys().last().unwrap_or(&0) + 1
+    // Keep the connection to the node, reconnecting when it's lost until
+    // giving up or the backend and its subscriptions are dropped
+    async fn run(
+        connect: impl Connect,
+        connection: (WsTx, WsRx),
+        mut requests: mpsc::Receiver<Id>,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+        state: Arc<Mutex<State>>,
+        options: Options,
+    ) {
+        let mut connection = Some(connection);
+        let mut attempts = 0;
+
+        loop {
+            if let Some(connection) = connection.take() {
+                let processed =
+                    Self::process_messages(connection, &mut requests, &mut commands, &state).await;
+                match processed {
+                    Some(true) => attempts = 0,
+                    Some(false) => {}
+                    None => {
+                        log::debug!("WS backend dropped");
+                        return;
+                    }
+                }
+            }
+
+            state.lock().await.disconnected(options.resend_requests);
+
+            if options
+                .max_reconnect_attempts
+                .is_some_and(|max| attempts >= max)
+            {
+                log::error!("WS giving up reconnecting after {} attempts", attempts);
+                requests.close();
+                commands.close();
+                state.lock().await.close();
+                return;
+            }
+
+            let delay = options
+                .reconnect_delay
+                .saturating_mul(1 << attempts.min(16))
+                .min(options.max_reconnect_delay);
+            log::info!("WS reconnecting in {:?}", delay);
+            sleep(delay).await;
+            attempts += 1;
+
+            connection = connect()
+                .map_err(|err| log::warn!("WS error connecting: {}", err))
+                .ok();
+        }
     }
 
-    pub async fn new_ws2<'a, U: Into<&'a str>>(url: U) -> core::result::Result<Self, Error> {
-        let url = url.into();
-        log::trace!("WS connecting to {}", url);
+    // Process the messages of a connection until it's closed, returns whether
+    // the connection was ever opened or `None` if the backend was dropped
+    async fn process_messages(
+        (mut tx, rx): (WsTx, WsRx),
+        requests: &mut mpsc::Receiver<Id>,
+        commands: &mut mpsc::UnboundedReceiver<Command>,
+        state: &Mutex<State>,
+    ) -> Option<bool> {
+        enum Event {
+            Ws(WsEvent),
+            Send(Id),
+            Command(Command),
+            Dropped,
+        }
 
-        let (tx, rx) =
-            ewebsock::connect(url, ewebsock::Options::default()).map_err(Error::Platform)?;
+        state.lock().await.connection += 1;
+        let mut opened = false;
 
-        let (sender, recv) = mpsc::channel::<Message>(MAX_BUFFER);
+        // subscriptions keep the connection alive after the backend is dropped
+        let commands = stream::select(requests.map(Event::Send), commands.map(Event::Command))
+            .chain(stream::once(future::ready(Event::Dropped)));
+        let mut events = stream::select(rx.map(Event::Ws), commands);
 
-        let backend = Backend {
-            tx: Mutex::new(sender),
-            ws_sender: Arc::new(Mutex::new(tx)),
-            messages: Arc::new(Mutex::new(BTreeMap::new())),
+        while let Some(event) = events.next().await {
+            match event {
+                Event::Ws(WsEvent::Message(Message::Text(msg))) => {
+                    log::trace!("Got WS message {:?}", msg);
+                    state.lock().await.process_message(&msg);
+                }
+                Event::Ws(WsEvent::Message(msg)) => {
+                    log::trace!("Got WS message {:?}", msg);
+                }
+                Event::Ws(WsEvent::Opened) => {
+                    log::trace!("Ws connection opened");
+                    opened = true;
+                    state.lock().await.resume(&mut tx);
+                }
+                Event::Ws(WsEvent::Error(e)) => {
+                    log::warn!("WS error {}", &e);
+                    break;
+                }
+                Event::Ws(WsEvent::Closed) => break,
+                // requests are sent once the connection is opened
+                Event::Send(id) if opened => state.lock().await.send(id, &mut tx),
+                Event::Send(_) => {}
+                Event::Command(cmd) => state.lock().await.process_command(cmd, &mut tx, opened),
+                Event::Dropped => return None,
+            }
+        }
+
+        log::info!("WS connection closed");
+        Some(opened)
+    }
+}
+
+// Native connections are driven by a task that forwards the messages
+// of the socket until either side closes the connection
+#[cfg(not(feature = "js"))]
+fn open(url: &str) -> Result<(WsTx, WsRx), String> {
+    use async_tungstenite::{async_std::connect_async, tungstenite::Message as WsMessage};
+    use future::Either;
+
+    let url = url.to_string();
+    let (tx, mut outgoing) = mpsc::unbounded::<Message>();
+    let (events, rx) = mpsc::unbounded::<WsEvent>();
+
+    spawn(async move {
+        let (mut sink, mut incoming) = match connect_async(url.as_str()).await {
+            Ok((socket, _)) => socket.split(),
+            Err(err) => {
+                let _ = events.unbounded_send(WsEvent::Error(err.to_string()));
+                return;
+            }
         };
+        let _ = events.unbounded_send(WsEvent::Opened);
 
-        let recv = Arc::new(Mutex::new(recv));
+        loop {
+            let event = match future::select(outgoing.next(), incoming.next()).await {
+                Either::Left((Some(msg), _)) => {
+                    let msg = match msg {
+                        Message::Text(text) => WsMessage::Text(text.into()),
+                        Message::Binary(data) => WsMessage::Binary(data.into()),
+                    };
+                    match sink.send(msg).await {
+                        Ok(()) => continue,
+                        Err(err) => WsEvent::Error(err.to_string()),
+                    }
+                }
+                // the backend dropped the connection
+                Either::Left((None, _)) => {
+                    let _ = sink.close(None).await;
+                    return;
+                }
+                Either::Right((Some(Ok(WsMessage::Text(text))), _)) => {
+                    WsEvent::Message(Message::Text(text.as_str().into()))
+                }
+                Either::Right((Some(Ok(WsMessage::Binary(data))), _)) => {
+                    WsEvent::Message(Message::Binary(data.to_vec()))
+                }
+                Either::Right((Some(Ok(WsMessage::Close(_))) | None, _)) => WsEvent::Closed,
+                // pings are answered by tungstenite
+                Either::Right((Some(Ok(_)), _)) => continue,
+                Either::Right((Some(Err(err)), _)) => WsEvent::Error(err.to_string()),
+            };
+            let done = matches!(event, WsEvent::Error(_) | WsEvent::Closed);
+            let _ = events.unbounded_send(event);
+            if done {
+                return;
+            }
+        }
+    });
 
-        backend.process_incoming_messages(rx, backend.ws_sender.clone(), recv.clone());
-        Ok(backend)
+    let tx: WsTx = Box::new(move |msg| {
+        let _ = tx.unbounded_send(msg);
+    });
+    Ok((tx, Box::pin(rx) as WsRx))
+}
+
+// Browsers connect with ewebsock
+#[cfg(feature = "js")]
+fn open(url: &str) -> Result<(WsTx, WsRx), String> {
+    use ewebsock::{WsEvent as Event, WsMessage};
+
+    let (mut tx, rx) = ewebsock::connect(url, ewebsock::Options::default())?;
+    let tx: WsTx = Box::new(move |msg| {
+        tx.send(match msg {
+            Message::Text(text) => WsMessage::Text(text),
+            Message::Binary(data) => WsMessage::Binary(data),
+        })
+    });
+    let rx = rx.filter_map(|event| {
+        future::ready(match event {
+            Event::Opened => Some(WsEvent::Opened),
+            Event::Message(WsMessage::Text(text)) => Some(WsEvent::Message(Message::Text(text))),
+            Event::Message(WsMessage::Binary(data)) => {
+                Some(WsEvent::Message(Message::Binary(data)))
+            }
+            Event::Message(_) => None,
+            Event::Error(err) => Some(WsEvent::Error(err)),
+            Event::Closed => Some(WsEvent::Closed),
+        })
+    });
+    Ok((tx, Box::pin(rx) as WsRx))
+}
+
+impl State {
+    fn send(&mut self, id: Id, tx: &mut WsTx) {
+        let Some(pending) = self.requests.get_mut(&id) else {
+            return;
+        };
+        if pending.sent == Some(self.connection) {
+            return;
+        }
+        pending.sent = Some(self.connection);
+        tx(Message::Text(pending.request.clone()));
     }
 
-    fn process_tx_send_messages(tx: Arc<Mutex<Tx>>, recv: Arc<Mutex<mpsc::Receiver<Message>>>) {
-        spawn(async move {
-            info!("waiting for coommands...");
+    fn remove(&mut self, id: Id) {
+        if let Some(sub) = self.requests.remove(&id).and_then(|p| p.subscription) {
+            self.subscriptions.remove(&sub);
+        }
+    }
 
-            while let Some(m) = recv.lock().await.next().await {
-                info!("got for coommands...?");
-                tx.lock().await.send(m);
+    fn process_command(&mut self, cmd: Command, tx: &mut WsTx, opened: bool) {
+        match cmd {
+            Command::Cancel(id) => self.remove(id),
+            Command::Unsubscribe(id) => {
+                let Some(sub) = self.subscriptions.remove(&id) else {
+                    return;
+                };
+                let Some(sub_id) = sub.id else {
+                    return;
+                };
+                self.subscription_ids.remove(&sub_id);
+                if opened {
+                    // responses to unsubscription requests are not awaited
+                    let id = serde_json::to_string(&sub_id).expect("valid id");
+                    tx(Message::Text(Backend::request(0, &sub.unsubscribe, &[&id])));
+                }
             }
+        }
+    }
+
+    fn process_message(&mut self, msg: &str) {
+        if let Ok(n) = serde_json::from_str::<Notification>(msg) {
+            let id = subscription_id(&n.params.subscription);
+            let Some(sub) = self
+                .subscription_ids
+                .get(&id)
+                .and_then(|id| self.subscriptions.get(id))
+            else {
+                log::warn!("Notification for unknown subscription {}", id);
+                return;
+            };
+            // the receiving end was dropped and it's being unsubscribed
+            let _ = sub.notifications.unbounded_send(n.params.result);
+            return;
+        }
+
+        let res: rpc::Response = serde_json::from_str(msg).unwrap_or_else(|_| {
+            result_to_response(
+                Err(standard_error(StandardError::ParseError, None)),
+                ().into(),
+            )
         });
+        let Some(id) = res.id.as_u64() else {
+            return;
+        };
+        log::trace!("Answering request {}", id);
+        let Some(pending) = self.requests.remove(&(id as Id)) else {
+            return;
+        };
+
+        if let Some(sub) = pending.subscription {
+            match res.result::<serde_json::Value>() {
+                Ok(sub_id) if self.subscriptions.contains_key(&sub) => {
+                    let sub_id = subscription_id(&sub_id);
+                    self.subscription_ids.insert(sub_id.clone(), sub);
+                    if let Some(s) = self.subscriptions.get_mut(&sub) {
+                        s.id = Some(sub_id);
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log::warn!("Subscription {} failed {}", sub, err);
+                    self.subscriptions.remove(&sub);
+                }
+            }
+        }
+
+        log::debug!("Answered request id: {}", id);
+        if let Some(channel) = pending.response {
+            if let Err(res) = channel.send(res) {
+                log::warn!("response error: {:?}", res);
+            }
+        }
     }
 
-    fn process_incoming_messages(
-        &self,
-        mut rx: Rx,
-        tx: Arc<Mutex<Tx>>,
-        recv: Arc<Mutex<mpsc::Receiver<Message>>>,
-    ) {
-        let messages = self.messages.clone();
-        spawn(async move {
-            while let Some(event) = rx.next().await {
-                match event {
-                    WsEvent::Message(msg) => {
-                        log::trace!("Got WS message {:?}", msg);
-
-                        if let Message::Text(msg) = msg {
-                            let res: rpc::Response =
-                                serde_json::from_str(&msg).unwrap_or_else(|_| {
-                                    result_to_response(
-                                        Err(standard_error(StandardError::ParseError, None)),
-                                        ().into(),
-                                    )
-                                });
-                            if res.id.is_u64() {
-                                let id = res.id.as_u64().unwrap() as Id;
-                                log::trace!("Answering request {}", id);
-                                let mut messages = messages.lock().await;
-                                if let Some(channel) = messages.remove(&id) {
-                                    log::debug!("Answered request id: {}", id);
-                                    if let Err(res) = channel.send(res) {
-                                        log::warn!("response error: {:?}", res);
-                                    }
+    // Subscribe again and send the requests that haven't been sent through
+    // the current connection
+    fn resume(&mut self, tx: &mut WsTx) {
+        let subscribing = self
+            .requests
+            .values()
+            .filter_map(|p| p.subscription)
+            .collect::<Vec<_>>();
+        let lost = self
+            .subscriptions
+            .iter()
+            .filter(|(id, sub)| sub.id.is_none() && !subscribing.contains(id))
+            .map(|(id, sub)| (*id, sub.method.clone(), sub.params.clone()))
+            .collect::<Vec<_>>();
+
+        for (sub, method, params) in lost {
+            let id = self.ids.next();
+            log::debug!("Subscribing again to `{}` (ID={})", method, id);
+            let params = params.iter().map(String::as_str).collect::<Vec<_>>();
+            let pending = Pending {
+                request: Backend::request(id, &method, &params),
+                response: None,
+                subscription: Some(sub),
+                sent: None,
+            };
+            self.requests.insert(id, pending);
+        }
+
+        let ids = self.requests.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.send(id, tx);
+        }
+    }
+
+    // Nodes forget about subscriptions once disconnected, requests without
+    // a response are either sent again or they fail
+    fn disconnected(&mut self, resend_requests: bool) {
+        self.subscription_ids.clear();
+        for sub in self.subscriptions.values_mut() {
+            sub.id = None;
+        }
+        if resend_requests {
+            return;
+        }
+
+        let requests = core::mem::take(&mut self.requests);
+        for (id, pending) in requests {
+            // subscriptions being re-established are retried on the next connection
+            let Some(channel) = pending.response else {
+                continue;
+            };
+            if let Some(sub) = pending.subscription {
+                self.subscriptions.remove(&sub);
+            }
+            let _ = channel.send(result_to_response(
+                Err(connection_error("Connection closed")),
+                id.into(),
+            ));
+        }
+    }
+
+    // Fail all requests and end all subscriptions
+    fn close(&mut self) {
+        self.disconnected(false);
+        self.requests.clear();
+        self.subscriptions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::join_all;
+    use serde_json::{json, Value as JsonValue};
+
+    // What the mock node does after receiving a request
+    enum Reply {
+        Result(JsonValue),
+        Notify(JsonValue, JsonValue),
+        Delay(Duration),
+        Close,
+    }
+
+    // In-process stand-in of a node, `handle` is called with the number of the
+    // connection and every request it receives, requests are answered concurrently
+    fn mock_node<H>(connections: Arc<AtomicU32>, handle: H) -> impl Connect
+    where
+        H: Fn(u32, &JsonValue) -> Vec<Reply> + Send + Sync + 'static,
+    {
+        let handle = Arc::new(handle);
+        move || {
+            let connection = connections.fetch_add(1, Ordering::SeqCst) + 1;
+            let (client_tx, mut requests) = mpsc::unbounded::<Message>();
+            let (events, client_rx) = mpsc::unbounded::<WsEvent>();
+            let handle = handle.clone();
+
+            spawn(async move {
+                let _ = events.unbounded_send(WsEvent::Opened);
+                while let Some(Message::Text(req)) = requests.next().await {
+                    let req: JsonValue = serde_json::from_str(&req).unwrap();
+                    let replies = handle(connection, &req);
+                    let events = events.clone();
+                    spawn(async move {
+                        for reply in replies {
+                            let msg = match reply {
+                                Reply::Result(result) => {
+                                    json!({ "jsonrpc": "2.0", "id": req["id"], "result": result })
                                 }
-                            }
+                                Reply::Notify(subscription, result) => json!({
+                                    "jsonrpc": "2.0",
+                                    "method": "notification",
+                                    "params": { "subscription": subscription, "result": result },
+                                }),
+                                Reply::Delay(delay) => {
+                                    sleep(delay).await;
+                                    continue;
+                                }
+                                Reply::Close => {
+                                    let _ = events.unbounded_send(WsEvent::Closed);
+                                    return;
+                                }
+                            };
+                            let msg = Message::Text(msg.to_string());
+                            let _ = events.unbounded_send(WsEvent::Message(msg));
                         }
-                    }
-                    WsEvent::Error(e) => {
-                        log::warn!("WS error {}", &e);
-                    }
-                    WsEvent::Closed => {
-                        log::info!("WS connection closed");
-                    }
-                    WsEvent::Opened => {
-                        log::info!("Processing tx msg");
-                        Backend::process_tx_send_messages(tx.clone(), recv.clone());
-                        log::trace!("Ws connection opened");
-                    }
+                    });
                 }
+            });
+
+            let tx: WsTx = Box::new(move |msg| {
+                let _ = client_tx.unbounded_send(msg);
+            });
+            Ok((tx, Box::pin(client_rx) as WsRx))
+        }
+    }
+
+    // Node listening on a local port that answers requests with the number of
+    // the connection, their method and params, it closes the connection after
+    // answering a `close` request
+    #[cfg(not(feature = "js"))]
+    async fn local_node() -> String {
+        use async_std::net::TcpListener;
+        use async_tungstenite::{accept_async, tungstenite::Message as WsMessage};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        spawn(async move {
+            let mut connection = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                connection += 1;
+                let mut socket = accept_async(stream).await.unwrap();
+                spawn(async move {
+                    while let Some(Ok(WsMessage::Text(req))) = socket.next().await {
+                        let req: JsonValue = serde_json::from_str(&req).unwrap();
+                        let result = json!([connection, req["method"], req["params"]]);
+                        let res = json!({ "jsonrpc": "2.0", "id": req["id"], "result": result });
+                        socket
+                            .send(WsMessage::Text(res.to_string().into()))
+                            .await
+                            .unwrap();
+                        if req["method"] == "close" {
+                            let _ = socket.close(None).await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn options() -> Options {
+        Options {
+            request_timeout: Some(Duration::from_secs(5)),
+            reconnect_delay: Duration::from_millis(10),
+            max_reconnect_delay: Duration::from_millis(50),
+            max_reconnect_attempts: Some(3),
+            resend_requests: true,
+        }
+    }
+
+    #[async_std::test]
+    async fn resend_requests_after_reconnecting() {
+        let connections = Arc::new(AtomicU32::new(0));
+        // the first connection is lost before answering
+        let node = mock_node(connections.clone(), |connection, _| match connection {
+            1 => vec![Reply::Close],
+            _ => vec![Reply::Result(json!("pong"))],
+        });
+        let backend = Backend::with_connector(node, options()).unwrap();
+
+        let res = backend.rpc::<String>("system_health", &[]).await.unwrap();
+        assert_eq!(res, "pong");
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[async_std::test]
+    async fn fail_requests_in_flight_without_resending() {
+        let connections = Arc::new(AtomicU32::new(0));
+        let node = mock_node(connections.clone(), |connection, _| match connection {
+            1 => vec![Reply::Close],
+            _ => vec![Reply::Result(json!("pong"))],
+        });
+        let options = Options {
+            resend_requests: false,
+            ..options()
+        };
+        let backend = Backend::with_connector(node, options).unwrap();
+
+        let err = backend.rpc::<String>("system_health", &[]).await;
+        assert!(err.unwrap_err().to_string().contains("Connection closed"));
+        // new requests are answered once reconnected
+        let res = backend.rpc::<String>("system_health", &[]).await.unwrap();
+        assert_eq!(res, "pong");
+    }
+
+    #[async_std::test]
+    async fn resubscribe_after_reconnecting() {
+        let connections = Arc::new(AtomicU32::new(0));
+        // subscriptions are notified of the connection number right away
+        let node = mock_node(connections.clone(), |connection, req| {
+            if req["method"] != "subscribe" {
+                return vec![Reply::Result(json!(true))];
             }
+            let id = json!(format!("sub{}", connection));
+            let mut replies = vec![
+                Reply::Result(id.clone()),
+                Reply::Notify(id, json!(connection)),
+            ];
+            if connection == 1 {
+                replies.push(Reply::Close);
+            }
+            replies
+        });
+        let backend = Backend::with_connector(node, options()).unwrap();
+
+        let mut sub = backend
+            .subscribe("subscribe", &[], "unsubscribe")
+            .await
+            .unwrap();
+        assert_eq!(sub.next().await, Some(json!(1)));
+        assert_eq!(sub.next().await, Some(json!(2)));
+    }
+
+    #[async_std::test]
+    async fn give_up_reconnecting() {
+        let connections = Arc::new(AtomicU32::new(0));
+        let node = mock_node(connections.clone(), |_, _| vec![Reply::Close]);
+        // the node is unreachable after the first connection
+        let connect = move || match connections.load(Ordering::SeqCst) {
+            0 => node(),
+            _ => Err("unreachable".into()),
+        };
+        let backend = Backend::with_connector(connect, options()).unwrap();
+
+        let err = backend.rpc::<String>("system_health", &[]).await;
+        assert!(err.unwrap_err().to_string().contains("Connection closed"));
+        let err = backend.rpc::<String>("system_health", &[]).await;
+        assert!(err.unwrap_err().to_string().contains("Connection closed"));
+    }
+
+    #[async_std::test]
+    async fn requests_time_out() {
+        let connections = Arc::new(AtomicU32::new(0));
+        let node = mock_node(connections, |_, _| vec![]);
+        let options = Options {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..options()
+        };
+        let backend = Backend::with_connector(node, options).unwrap();
+
+        let err = backend.rpc::<String>("system_health", &[]).await;
+        assert!(err.unwrap_err().to_string().contains("Request timed out"));
+    }
+
+    #[async_std::test]
+    async fn concurrent_requests() {
+        let connections = Arc::new(AtomicU32::new(0));
+        // requests are answered out of order with their parameter
+        let node = mock_node(connections, |_, req| {
+            let n = req["params"][0].as_u64().unwrap();
+            vec![
+                Reply::Delay(Duration::from_micros(n * 7919 % 2000)),
+                Reply::Result(req["params"][0].clone()),
+            ]
+        });
+        let backend = Arc::new(Backend::with_connector(node, options()).unwrap());
+
+        let queries = (0..1000u32).map(|n| {
+            let backend = backend.clone();
+            spawn(async move {
+                let res = backend.rpc::<u32>("echo", &[&n.to_string()]).await;
+                (n, res.unwrap())
+            })
+        });
+        for (n, res) in join_all(queries).await {
+            assert_eq!(n, res);
+        }
+        assert!(backend.state.lock().await.requests.is_empty());
+    }
+
+    #[async_std::test]
+    async fn cancel_dropped_requests() {
+        let connections = Arc::new(AtomicU32::new(0));
+        let node = mock_node(connections, |_, req| match req["method"].as_str() {
+            Some("slow") => vec![Reply::Delay(Duration::from_secs(5))],
+            _ => vec![Reply::Result(json!(true))],
+        });
+        let options = Options {
+            request_timeout: None,
+            ..options()
+        };
+        let backend = Backend::with_connector(node, options).unwrap();
+
+        let slow = backend.rpc::<bool>("slow", &[]);
+        assert!(timeout(Duration::from_millis(20), slow).await.is_err());
+        // the cancellation is processed before later requests are answered
+        assert!(backend.rpc::<bool>("fast", &[]).await.unwrap());
+        assert!(backend.state.lock().await.requests.is_empty());
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[async_std::test]
+    async fn talk_to_a_local_node() {
+        let backend = Backend::connect(local_node().await.as_str(), options()).unwrap();
+
+        let res = backend.rpc::<JsonValue>("chain_getBlockHash", &["1"]).await;
+        assert_eq!(res.unwrap(), json!([1, "chain_getBlockHash", [1]]));
+        let res = backend.rpc::<JsonValue>("close", &[]).await;
+        assert_eq!(res.unwrap(), json!([1, "close", []]));
+        // the closed connection is replaced by a new one
+        let res = backend.rpc::<JsonValue>("system_name", &[]).await;
+        assert_eq!(res.unwrap(), json!([2, "system_name", []]));
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[async_std::test]
+    async fn fail_to_connect_to_a_local_node() {
+        let listener = async_std::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let backend = Backend::connect(url.as_str(), options()).unwrap();
+
+        let err = backend.rpc::<JsonValue>("system_name", &[]).await;
+        assert!(err.unwrap_err().to_string().contains("Connection closed"));
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[async_std::test]
+    async fn concurrent_requests_to_a_local_node() {
+        let backend = Backend::connect(local_node().await.as_str(), options()).unwrap();
+        let backend = Arc::new(backend);
 
-            log::warn!("WS connection closed");
+        let queries = (0..200u32).map(|n| {
+            let backend = backend.clone();
+            spawn(async move {
+                let res = backend.rpc::<JsonValue>("echo", &[&n.to_string()]).await;
+                (n, res.unwrap())
+            })
         });
+        for (n, res) in join_all(queries).await {
+            assert_eq!(res, json!([1, "echo", [n]]));
+        }
+        assert!(backend.state.lock().await.requests.is_empty());
     }
 }
@@ -50,7 +50,10 @@ pub async fn sube_js(
             .map_err(|e| JsError::new(&format!("Error querying: {:?}", &e.to_string())))?;
 
         let value = match response {
-            v @ Response::Value(_) | v @ Response::Meta(_) | v @ Response::Registry(_) => {
+            v @ Response::Value(_)
+            | v @ Response::Meta(_)
+            | v @ Response::Registry(_)
+            | v @ Response::Events(_) => {
                 let value = serde_wasm_bindgen::to_value(&v)
                     .map_err(|_| JsError::new("failed to serialize response"))?;
                 Ok(value)
@@ -67,7 +70,7 @@ pub async fn sube_js(
 
     log::info!("new extrinsic_value: {:?}", extrinsic_value);
 
-    let signer = sube::SignerFn::from((
+    let signer = sube::SignerFn::try_from((
         extrinsic_value.from,
         |message: &[u8]| {
             let message = message.to_vec();
@@ -95,7 +98,8 @@ pub async fn sube_js(
                     Ok(buffer)
             }
         },
-    ));
+    ))
+    .map_err(|e| JsError::new(&format!("Invalid account: {:?}", e.to_string())))?;
 
     let value = SubeBuilder::default()
         .with_url(url)